@@ -1,7 +1,7 @@
 use std::{
-    fs::{self, File},
-    io::{Error, ErrorKind, Read, Result},
-    path::Path,
+    fs,
+    io::{Error, Read, Result},
+    path::{Path, PathBuf},
 };
 
 use codegen::Module;
@@ -10,16 +10,187 @@ use serde::{de::Visitor, Deserialize, Deserializer};
 use crate::{
     command_parser::CommandDetails,
     generator::{
-        generate_execute_fn, generate_group_definition, generate_group_impl,
-        generate_item_definition, generate_item_impl, generate_parse_item, generate_parse_items,
-        generate_parse_task, generate_task_impl, generate_task_struct,
+        generate_command_line_fn, generate_describe_fn, generate_execute_fns,
+        generate_group_definition, generate_group_impl, generate_item_definition,
+        generate_item_expand, generate_item_impl, generate_parse_item, generate_parse_items,
+        generate_parse_task, generate_select_variant_fn, generate_task_impl, generate_task_struct,
     },
 };
 
+/// Parameter types are written as a name (`path`, `list<int>`) or as
+/// `enum: [a, b, c]`.
 #[derive(Debug, Deserialize)]
-#[serde(rename_all = "lowercase")]
+#[serde(untagged)]
+enum RawParameterType {
+    Name(String),
+    Enum {
+        #[serde(rename = "enum")]
+        values: Vec<String>,
+    },
+}
+
+/// Values accepted for `octal_mode` parameters, as an XML Schema pattern,
+/// which always has to match the whole value.
+pub const OCTAL_MODE_PATTERN: &str = "(0o?)?[0-7]{1,4}";
+
+#[derive(Debug, Deserialize, PartialEq, Eq)]
+#[serde(try_from = "RawParameterType")]
 pub enum ParameterType {
     Path,
+    String,
+    Bool,
+    Int,
+    OctalMode,
+    Enum(Vec<String>),
+    List(Box<ParameterType>),
+}
+
+impl TryFrom<RawParameterType> for ParameterType {
+    type Error = String;
+
+    fn try_from(raw: RawParameterType) -> std::result::Result<ParameterType, String> {
+        match raw {
+            RawParameterType::Name(name) => ParameterType::from_name(&name),
+            RawParameterType::Enum { values } => Ok(ParameterType::Enum(values)),
+        }
+    }
+}
+
+impl ParameterType {
+    fn from_name(name: &str) -> std::result::Result<ParameterType, String> {
+        let name = name.trim();
+        if let Some(inner) = name
+            .strip_prefix("list<")
+            .and_then(|inner| inner.strip_suffix('>'))
+        {
+            return match ParameterType::from_name(inner)? {
+                ParameterType::List(_) => Err(String::from("Lists cannot be nested")),
+                inner => Ok(ParameterType::List(Box::new(inner))),
+            };
+        }
+        match name {
+            "path" => Ok(ParameterType::Path),
+            "string" => Ok(ParameterType::String),
+            "bool" => Ok(ParameterType::Bool),
+            "int" => Ok(ParameterType::Int),
+            "octal_mode" => Ok(ParameterType::OctalMode),
+            _ => Err(format!("Unknown parameter type '{}'", name)),
+        }
+    }
+
+    pub fn is_list(&self) -> bool {
+        matches!(self, ParameterType::List(_))
+    }
+
+    pub fn rust_type(&self) -> String {
+        match self {
+            ParameterType::Path => String::from("PathBuf"),
+            ParameterType::String | ParameterType::Enum(_) => String::from("String"),
+            ParameterType::Bool => String::from("bool"),
+            ParameterType::Int => String::from("i64"),
+            ParameterType::OctalMode => String::from("FileMode"),
+            ParameterType::List(inner) => format!("Vec<{}>", inner.rust_type()),
+        }
+    }
+
+    pub fn xsd_type(&self) -> &'static str {
+        match self {
+            ParameterType::Path | ParameterType::String | ParameterType::Enum(_) => "xs:string",
+            ParameterType::Bool => "bf:bool",
+            ParameterType::Int => "xs:integer",
+            ParameterType::OctalMode => "bf:octal_mode",
+            ParameterType::List(_) => "xs:string",
+        }
+    }
+
+    /// Expression reading the raw value(s) of the parameter from `element`.
+    pub fn reader(&self, name: &str) -> String {
+        if self.is_list() {
+            format!(r#"interpolate_list("{}", element, runtime)?"#, name)
+        } else {
+            format!(r#"interpolate_attribute("{}", element, runtime)?"#, name)
+        }
+    }
+
+    /// Expression converting the string `value` into a `Result` of this type.
+    fn parse_expression(&self, name: &str, value: &str) -> String {
+        match self {
+            ParameterType::Path => format!("Ok(PathBuf::from({}))", value),
+            ParameterType::String => format!("Ok({})", value),
+            ParameterType::Bool => format!("parse_bool(\"{}\", &{})", name, value),
+            ParameterType::Int => format!("parse_int(\"{}\", &{})", name, value),
+            ParameterType::OctalMode => format!("parse_octal_mode(\"{}\", &{})", name, value),
+            ParameterType::Enum(values) => {
+                let values = values
+                    .iter()
+                    .map(|value| format!("\"{}\"", value))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                format!("parse_enum(\"{}\", {}, &[{}])", name, value, values)
+            }
+            ParameterType::List(_) => unreachable!("Lists cannot be nested"),
+        }
+    }
+
+    pub fn conversion_suffix(&self, name: &str) -> String {
+        match self {
+            ParameterType::Path => String::from(".map(PathBuf::from)"),
+            ParameterType::String => String::new(),
+            ParameterType::List(inner) if **inner == ParameterType::String => String::new(),
+            ParameterType::List(inner) => format!(
+                ".map(|values| values.into_iter().map(|value| {}).collect::<Result<Vec<_>, Error>>()).transpose()?",
+                inner.parse_expression(name, "value")
+            ),
+            _ => format!(
+                ".map(|value| {}).transpose()?",
+                self.parse_expression(name, "value")
+            ),
+        }
+    }
+
+    /// Expression passing `value` to `Command::arg`.
+    pub fn argument(&self, value: &str) -> String {
+        match self {
+            ParameterType::Path | ParameterType::String | ParameterType::Enum(_) => {
+                format!("&{}", value)
+            }
+            _ => self.bound_argument(value),
+        }
+    }
+
+    /// Like `argument`, for a `value` that is already a reference.
+    pub fn bound_argument(&self, value: &str) -> String {
+        match self {
+            ParameterType::Path | ParameterType::String | ParameterType::Enum(_) => {
+                String::from(value)
+            }
+            ParameterType::List(_) => unreachable!("Lists are passed with Command::args"),
+            _ => format!("{}.to_string()", value),
+        }
+    }
+
+    /// Helpers from `crate::util` the generated code needs for this type.
+    pub fn imports(&self) -> Vec<&'static str> {
+        match self {
+            ParameterType::Path | ParameterType::String => vec![],
+            ParameterType::Bool => vec!["parse_bool"],
+            ParameterType::Int => vec!["parse_int"],
+            ParameterType::OctalMode => vec!["parse_octal_mode", "FileMode"],
+            ParameterType::Enum(_) => vec!["parse_enum"],
+            ParameterType::List(inner) => {
+                let mut imports = inner.imports();
+                imports.push("interpolate_list");
+                imports
+            }
+        }
+    }
+
+    pub fn is_copy(&self) -> bool {
+        matches!(
+            self,
+            ParameterType::Bool | ParameterType::Int | ParameterType::OctalMode
+        )
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -29,6 +200,24 @@ pub enum GroupSetting {
     Inherit,
     Prefix,
     InheritPrefix,
+    Append,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ParameterRole {
+    Source,
+    Destination,
+}
+
+impl ParameterRole {
+    /// Field of `Runtime` holding the base applied to paths with this role.
+    pub fn base(&self) -> &'static str {
+        match self {
+            ParameterRole::Source => "source_base",
+            ParameterRole::Destination => "destination_base",
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -39,6 +228,7 @@ pub struct ParameterDescriptor {
     pub allow_group: GroupSetting,
     pub defaults_to: Option<String>,
     pub required: bool,
+    pub role: Option<ParameterRole>,
 }
 
 #[macro_export]
@@ -75,65 +265,301 @@ impl<'de> Visitor<'de> for CommandDetailsVisitor {
     }
 }
 
-fn parse_command<'de, D>(deserializer: D) -> std::result::Result<CommandDetails, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    deserializer.deserialize_str(CommandDetailsVisitor)
+impl<'de> Deserialize<'de> for CommandDetails {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<CommandDetails, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(CommandDetailsVisitor)
+    }
+}
+
+/// A command line used when the target matches every key that is set. `os`
+/// and `arch` are compared with the variables of the same name and
+/// `condition` is evaluated like any other condition.
+#[derive(Debug, Deserialize)]
+pub struct CommandVariant {
+    pub os: Option<String>,
+    pub arch: Option<String>,
+    pub condition: Option<String>,
+    pub command: CommandDetails,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawCommandLineDescriptor {
+    #[serde(default)]
+    variants: Vec<CommandVariant>,
+    default: Option<CommandDetails>,
+    linux: Option<CommandDetails>,
+    windows: Option<CommandDetails>,
+    macos: Option<CommandDetails>,
 }
 
+/// Command line variants, tried in order at runtime, and the command used when
+/// none of them matches. The `linux`, `windows` and `macos` shorthands become
+/// variants keyed by `os`, after the explicit ones.
 #[derive(Debug, Deserialize)]
+#[serde(from = "RawCommandLineDescriptor")]
 pub struct CommandLineDescriptor {
-    #[serde(deserialize_with = "parse_command")]
-    pub linux: CommandDetails,
-    #[serde(deserialize_with = "parse_command")]
-    pub windows: CommandDetails,
-    #[serde(deserialize_with = "parse_command")]
-    pub macos: CommandDetails,
+    pub variants: Vec<CommandVariant>,
+    pub default: Option<CommandDetails>,
+}
+
+impl From<RawCommandLineDescriptor> for CommandLineDescriptor {
+    fn from(raw: RawCommandLineDescriptor) -> CommandLineDescriptor {
+        let mut variants = raw.variants;
+        for (os, command) in [
+            ("linux", raw.linux),
+            ("windows", raw.windows),
+            ("macos", raw.macos),
+        ] {
+            if let Some(command) = command {
+                variants.push(CommandVariant {
+                    os: Some(String::from(os)),
+                    arch: None,
+                    condition: None,
+                    command,
+                });
+            }
+        }
+        CommandLineDescriptor {
+            variants,
+            default: raw.default,
+        }
+    }
+}
+
+impl CommandLineDescriptor {
+    /// Every command line, variants first, in the order they are indexed.
+    pub fn commands(&self) -> impl Iterator<Item = &CommandDetails> {
+        self.variants
+            .iter()
+            .map(|variant| &variant.command)
+            .chain(self.default.iter())
+    }
+}
+
+/// Code used when `bf` is built for a target matching every key that is set.
+/// `os`, `arch` and `family` are checked like `target_os`, `target_arch` and
+/// `target_family` in a `#[cfg]` attribute.
+#[derive(Debug, Deserialize)]
+pub struct SnippetVariant {
+    pub os: Option<String>,
+    pub arch: Option<String>,
+    pub family: Option<String>,
+    pub code: String,
+}
+
+impl SnippetVariant {
+    /// Configuration predicate matching the variant's keys.
+    pub fn cfg(&self) -> String {
+        let predicates = [
+            ("target_os", &self.os),
+            ("target_arch", &self.arch),
+            ("target_family", &self.family),
+        ]
+        .iter()
+        .filter_map(|(key, value)| {
+            value
+                .as_ref()
+                .map(|value| format!("{} = \"{}\"", key, value))
+        })
+        .collect::<Vec<String>>();
+        match &predicates[..] {
+            [predicate] => predicate.clone(),
+            _ => format!("all({})", predicates.join(", ")),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum RawSnippetDescriptor {
+    Code(String),
+    Variants {
+        #[serde(default)]
+        variants: Vec<SnippetVariant>,
+        default: Option<String>,
+        linux: Option<String>,
+        windows: Option<String>,
+        macos: Option<String>,
+        unix: Option<String>,
+    },
+}
+
+/// Snippet bodies for different targets, tried in order when `bf` is built,
+/// and the code used when none of them matches. A plain string is the default
+/// for every target. The `linux`, `windows` and `macos` shorthands become
+/// variants keyed by `os`, and `unix` one keyed by `family`, after the
+/// explicit ones.
+#[derive(Debug, Deserialize)]
+#[serde(from = "RawSnippetDescriptor")]
+pub struct SnippetDescriptor {
+    pub variants: Vec<SnippetVariant>,
+    pub default: Option<String>,
+}
+
+impl From<RawSnippetDescriptor> for SnippetDescriptor {
+    fn from(raw: RawSnippetDescriptor) -> SnippetDescriptor {
+        match raw {
+            RawSnippetDescriptor::Code(code) => SnippetDescriptor {
+                variants: Vec::new(),
+                default: Some(code),
+            },
+            RawSnippetDescriptor::Variants {
+                mut variants,
+                default,
+                linux,
+                windows,
+                macos,
+                unix,
+            } => {
+                for (os, code) in [("linux", linux), ("windows", windows), ("macos", macos)] {
+                    if let Some(code) = code {
+                        variants.push(SnippetVariant {
+                            os: Some(String::from(os)),
+                            arch: None,
+                            family: None,
+                            code,
+                        });
+                    }
+                }
+                if let Some(code) = unix {
+                    variants.push(SnippetVariant {
+                        os: None,
+                        arch: None,
+                        family: Some(String::from("unix")),
+                        code,
+                    });
+                }
+                SnippetDescriptor { variants, default }
+            }
+        }
+    }
+}
+
+impl From<&str> for SnippetDescriptor {
+    fn from(code: &str) -> SnippetDescriptor {
+        SnippetDescriptor::from(RawSnippetDescriptor::Code(String::from(code)))
+    }
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum Command {
-    Snippet(String),
+    Snippet(SnippetDescriptor),
     CommandLine(CommandLineDescriptor),
 }
 
 #[derive(Debug, Deserialize)]
 pub struct ElementDescriptor {
     pub attributes: Vec<ParameterDescriptor>,
+    pub text: Option<String>,
 }
 
 impl ElementDescriptor {
-    pub fn uses_groups(&self) -> bool {
-        let allows_group = |parameter_descriptor: &ParameterDescriptor| -> bool {
-            match parameter_descriptor.allow_group {
-                GroupSetting::None => false,
-                _ => true,
-            }
-        };
-        let attr_allows = self
-            .attributes
+    pub fn has_role(&self, role: ParameterRole) -> bool {
+        self.attributes
             .iter()
-            .map(allows_group)
-            .find(|predicate| *predicate);
-        attr_allows.unwrap_or(false)
+            .any(|attribute| attribute.role == Some(role))
     }
 }
 
 #[derive(Debug, Deserialize)]
 pub struct TaskDescriptor {
     pub name: String,
+    pub description: Option<String>,
     pub command: Command,
     pub element: ElementDescriptor,
 }
 
 impl TaskDescriptor {
-    fn from_reader<R: Read>(reader: R) -> Result<TaskDescriptor> {
-        serde_yaml::from_reader(reader).map_err(|e| Error::new(ErrorKind::Other, e.to_string()))
+    pub fn from_reader<R: Read>(reader: R) -> Result<TaskDescriptor> {
+        let descriptor: TaskDescriptor =
+            serde_yaml::from_reader(reader).map_err(|e| Error::other(e.to_string()))?;
+        descriptor.validate()?;
+        Ok(descriptor)
+    }
+
+    fn validate(&self) -> Result<()> {
+        for attribute in &self.element.attributes {
+            let prefixed = matches!(
+                attribute.allow_group,
+                GroupSetting::Prefix | GroupSetting::InheritPrefix
+            );
+            if attribute.parameter_type != ParameterType::Path
+                && (prefixed || attribute.role.is_some())
+            {
+                return Err(invalid!(
+                    "Only path attributes can have a role or be prefixed"
+                )());
+            }
+            if attribute.parameter_type == ParameterType::Enum(Vec::new()) {
+                return Err(invalid!("Enum attributes must list their values")());
+            }
+            if attribute.role.is_some() && !prefixed {
+                return Err(invalid!(
+                    "Attributes with a role must use the prefix or inherit_prefix group setting"
+                )());
+            }
+            let appended = matches!(attribute.allow_group, GroupSetting::Append);
+            if appended && !attribute.parameter_type.is_list() {
+                return Err(invalid!("Only list attributes can be appended")());
+            }
+        }
+        if let Some(text) = &self.element.text {
+            let attribute = self
+                .element
+                .attributes
+                .iter()
+                .find(|attribute| &attribute.name == text)
+                .ok_or_else(invalid!("Element text maps to an undeclared attribute"))?;
+            if attribute.parameter_type.is_list() {
+                return Err(invalid!("Element text cannot map to a list attribute")());
+            }
+        }
+        match &self.command {
+            Command::CommandLine(command_line) => {
+                if command_line.commands().next().is_none() {
+                    return Err(invalid!("Command line tasks need a variant or a default")());
+                }
+                for details in command_line.commands() {
+                    self.validate_command_line(details)?;
+                }
+            }
+            Command::Snippet(snippet) => {
+                if snippet.variants.is_empty() && snippet.default.is_none() {
+                    return Err(invalid!("Snippet tasks need a variant or a default")());
+                }
+            }
+        }
+        Ok(())
     }
 
-    pub fn load_from_directory(tasks_path: &Path) -> Result<Vec<TaskDescriptor>> {
+    /// An optional part is repeated for every value of the list it references,
+    /// so it can reference at most one.
+    fn validate_command_line(&self, details: &CommandDetails) -> Result<()> {
+        for part in details.parts.iter().filter(|part| part.optional) {
+            let lists = part
+                .dependencies
+                .iter()
+                .filter(|dependency| {
+                    self.element.attributes.iter().any(|attribute| {
+                        &&attribute.name == dependency && attribute.parameter_type.is_list()
+                    })
+                })
+                .count();
+            if lists > 1 {
+                return Err(invalid!(
+                    "An optional command line part can reference at most one list"
+                )());
+            }
+        }
+        Ok(())
+    }
+
+    /// Descriptor files in `tasks_path`, in the order tasks are generated.
+    pub fn paths_in_directory(tasks_path: &Path) -> Result<Vec<PathBuf>> {
         fs::read_dir(tasks_path)?
             .map(|entry| entry.map(|e| e.path()))
             .filter_map(|entry| match entry {
@@ -147,11 +573,6 @@ impl TaskDescriptor {
                 }
                 Err(err) => Some(Err(err)),
             })
-            .map(|path| {
-                let path = path?;
-                let file = File::open(path)?;
-                TaskDescriptor::from_reader(file)
-            })
             .collect()
     }
 
@@ -159,29 +580,74 @@ impl TaskDescriptor {
         let group_struct = generate_group_definition(&self.element);
         let group_impl = generate_group_impl(&self.element);
         let item_struct = generate_item_definition(&self.element);
-        let item_impl = generate_item_impl(&self.element);
-        let task_struct = generate_task_struct();
+        let item_impl = generate_item_impl(&self.element)
+            .push_fn(generate_item_expand(&self.element))
+            .to_owned();
+        let task_struct = generate_task_struct(self);
         let task_impl = generate_task_impl(&self);
-        let execute_fn = generate_execute_fn(&self);
+        let execute_fns = generate_execute_fns(self);
+        let describe_fn = generate_describe_fn(self);
         let parse_item_fn = generate_parse_item();
-        let parse_items_fn = generate_parse_items();
-        let parse_task_fn = generate_parse_task();
-        Module::new(&self.name)
+        let parse_items_fn = generate_parse_items(&self.element);
+        let parse_task_fn = generate_parse_task(self);
+        let mut module = Module::new(&self.name);
+        module
             .import("std::path", "PathBuf")
             .import("minidom", "Element")
             .import("crate::runtime", "Runtime")
             .import("crate::error", "Error")
+            .import("crate::error", "ErrorContext")
+            .import("crate::error", "Diagnostics")
             .import("crate::util", "interpolate_attribute")
             .import("crate::util", "ApplyPrefix")
             .import("crate::util", "evaluate_condition_from_element")
+            .import("crate::util", "Exclusions")
+            .import("crate::util", "check_item")
             .import("crate", "internal_error")
             .push_struct(group_struct)
             .push_impl(group_impl)
             .push_struct(item_struct)
             .push_impl(item_impl)
             .push_struct(task_struct)
-            .push_impl(task_impl)
-            .push_fn(execute_fn)
+            .push_impl(task_impl);
+        if self.element.has_role(ParameterRole::Source) {
+            module
+                .import("crate::util", "expand_glob")
+                .import("crate::util", "glob_required");
+            if self.element.has_role(ParameterRole::Destination) {
+                module.import("crate::util", "resolve_destination");
+            }
+        }
+        for attribute in &self.element.attributes {
+            for name in attribute.parameter_type.imports() {
+                module.import("crate::util", name);
+            }
+            if matches!(attribute.allow_group, GroupSetting::Append) {
+                module.import("crate::util", "append_list");
+            }
+        }
+        if self.element.text.is_some() {
+            module.import("crate::util", "interpolate_text");
+        }
+        match &self.command {
+            Command::CommandLine(command_line) => {
+                module
+                    .import("std::process", "Command")
+                    .import("crate::util", "select_command_variant")
+                    .push_fn(generate_select_variant_fn(self, command_line))
+                    .push_fn(generate_command_line_fn(command_line, &self.element));
+            }
+            Command::Snippet(_) => {
+                if self.description.is_some() {
+                    module.import("crate::util", "Describe");
+                }
+            }
+        }
+        for execute_fn in execute_fns {
+            module.push_fn(execute_fn);
+        }
+        module
+            .push_fn(describe_fn)
             .push_fn(parse_item_fn)
             .push_fn(parse_items_fn)
             .push_fn(parse_task_fn)
@@ -44,7 +44,7 @@ impl CommandDetails {
                     dependencies: dependencies,
                     optional: true,
                 });
-                arguments = &arguments[index + 1..];
+                arguments = arguments[index + 1..].trim_start();
             } else {
                 let index = arguments.find("[");
                 let part = if let Some(index) = index {
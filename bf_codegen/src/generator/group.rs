@@ -1,14 +1,12 @@
 use codegen::{Block, Field, Function, Impl, Struct, Type};
 
-use crate::command::{ElementDescriptor, GroupSetting, ParameterDescriptor, ParameterType};
+use crate::command::{ElementDescriptor, GroupSetting, ParameterDescriptor};
 
 fn generate_field_definition(parameter: &ParameterDescriptor) -> Option<Field> {
     match parameter.allow_group {
         GroupSetting::None => None,
         _ => {
-            let inner_type = match parameter.parameter_type {
-                ParameterType::Path => "PathBuf",
-            };
+            let inner_type = parameter.parameter_type.rust_type();
             let field_type = Type::new("Option").generic(inner_type).to_owned();
             Some(Field::new(&parameter.name, field_type))
         }
@@ -23,25 +21,31 @@ pub fn generate_group_definition(element_descriptor: &ElementDescriptor) -> Stru
             struct_definition.push_field(field);
         }
     }
+    struct_definition.field("exclude", "Exclusions");
     struct_definition
 }
 
 fn add_parameter_code(function: &mut Function, parameter: &ParameterDescriptor) {
-    let conversion_suffix = match &parameter.parameter_type {
-        ParameterType::Path => ".map(PathBuf::from)",
-    };
+    let conversion_suffix = parameter.parameter_type.conversion_suffix(&parameter.name);
     let init_line = format!(
-        r#"let {var_name} = interpolate_attribute("{var_name}", element, runtime)?{suffix};"#,
+        r#"let {var_name} = {reader}{suffix};"#,
         var_name = parameter.name,
+        reader = parameter.parameter_type.reader(&parameter.name),
         suffix = conversion_suffix
     );
     match parameter.allow_group {
         GroupSetting::None => (),
         GroupSetting::Inherit => {
             function.line(init_line);
+            let clone = if parameter.parameter_type.is_copy() {
+                ""
+            } else {
+                ".clone()"
+            };
             function.line(format!(
-                "let {var_name} = {var_name}.or(parent.map(|group| group.{var_name}));",
-                var_name = parameter.name
+                "let {var_name} = {var_name}.or(parent.and_then(|group| group.{var_name}{clone}));",
+                var_name = parameter.name,
+                clone = clone
             ));
         }
         GroupSetting::Prefix | GroupSetting::InheritPrefix => {
@@ -55,11 +59,25 @@ fn add_parameter_code(function: &mut Function, parameter: &ParameterDescriptor)
                 var_name = parameter.name
             ));
             let mut else_block = Block::new("else");
-            else_block.line(format!("{var_name}", var_name = parameter.name));
+            match parameter.role {
+                Some(role) => else_block.line(format!(
+                    "{var_name}.apply_prefix(&runtime.{base})",
+                    var_name = parameter.name,
+                    base = role.base()
+                )),
+                None => else_block.line(&parameter.name),
+            };
             else_block.after(";");
             function.push_block(if_block);
             function.push_block(else_block);
         }
+        GroupSetting::Append => {
+            function.line(init_line);
+            function.line(format!(
+                "let {var_name} = append_list(parent.and_then(|group| group.{var_name}.as_ref()), {var_name});",
+                var_name = parameter.name
+            ));
+        }
     }
 }
 
@@ -67,34 +85,32 @@ pub fn generate_group_impl(element_descriptor: &ElementDescriptor) -> Impl {
     let mut create_function = Function::new("create");
     create_function
         .vis("pub")
-        .ret(Type::new("Result<Group, Error>"));
-    if element_descriptor.uses_groups() {
-        create_function
-            .arg("element", Type::new("&Element"))
-            .arg("parent", Type::new("Option<&Group>"))
-            .arg("runtime", Type::new("&Runtime"));
-        let mut constructor = Block::new("Ok(Group");
-        constructor.after(")");
-        for attribute in &element_descriptor.attributes {
-            add_parameter_code(&mut create_function, attribute);
-            if !matches!(attribute.allow_group, GroupSetting::None) {
-                constructor.line(format!(
-                    "{var_name}: {var_name},",
-                    var_name = attribute.name
-                ));
-            }
+        .ret(Type::new("Result<Group, Error>"))
+        .arg("element", Type::new("&Element"))
+        .arg("parent", Type::new("Option<&Group>"))
+        .arg("runtime", Type::new("&Runtime"));
+    let mut constructor = Block::new("Ok(Group");
+    constructor.after(")");
+    for attribute in &element_descriptor.attributes {
+        add_parameter_code(&mut create_function, attribute);
+        if !matches!(attribute.allow_group, GroupSetting::None) {
+            constructor.line(format!("{},", attribute.name));
         }
-        create_function.push_block(constructor);
-    } else {
-        create_function.line("Ok(Group{})");
     }
+    create_function.line(
+        "let exclude = Exclusions::create(element, parent.map(|group| &group.exclude), runtime)?;",
+    );
+    constructor.line("exclude,");
+    create_function.push_block(constructor);
     Impl::new("Group").push_fn(create_function).to_owned()
 }
 
 #[cfg(test)]
 mod test {
     use crate::{
-        command::{ElementDescriptor, GroupSetting, ParameterDescriptor, ParameterType},
+        command::{
+            ElementDescriptor, GroupSetting, ParameterDescriptor, ParameterRole, ParameterType,
+        },
         generator::test_utils,
     };
 
@@ -110,6 +126,7 @@ mod test {
             required: required,
             allow_group: allow_group,
             defaults_to: None,
+            role: None,
         }
     }
 
@@ -124,6 +141,7 @@ mod test {
                 new_parameter("dst", ParameterType::Path, false, setting2),
                 new_parameter("tst", ParameterType::Path, true, setting3),
             ],
+            text: None,
         }
     }
 
@@ -134,7 +152,9 @@ mod test {
         let item = super::generate_group_definition(&descriptor);
         const EXPECTED: &str = r#"
         #[derive(Debug)]
-        struct Group;"#;
+        struct Group {
+            exclude: Exclusions,
+        }"#;
         test_utils::compare_struct(item, EXPECTED);
     }
 
@@ -149,6 +169,7 @@ mod test {
             src: Option<PathBuf>,
             dst: Option<PathBuf>,
             tst: Option<PathBuf>,
+            exclude: Exclusions,
         }"#;
         test_utils::compare_struct(item, EXPECTED);
     }
@@ -163,6 +184,7 @@ mod test {
         struct Group {
             src: Option<PathBuf>,
             tst: Option<PathBuf>,
+            exclude: Exclusions,
         }"#;
         test_utils::compare_struct(item, EXPECTED);
     }
@@ -174,8 +196,11 @@ mod test {
         let item = super::generate_group_impl(&descriptor);
         const EXPECTED: &str = r#"
         impl Group {
-            pub fn create() -> Result<Group, Error> {
-                Ok(Group{})
+            pub fn create(element: &Element, parent: Option<&Group>, runtime: &Runtime) -> Result<Group, Error> {
+                let exclude = Exclusions::create(element, parent.map(|group| &group.exclude), runtime)?;
+                Ok(Group {
+                    exclude,
+                })
             }
         }"#;
         test_utils::compare_impl(item, EXPECTED);
@@ -190,15 +215,17 @@ mod test {
         impl Group {
             pub fn create(element: &Element, parent: Option<&Group>, runtime: &Runtime) -> Result<Group, Error> {
                 let src = interpolate_attribute("src", element, runtime)?.map(PathBuf::from);
-                let src = src.or(parent.map(|group| group.src));
+                let src = src.or(parent.and_then(|group| group.src.clone()));
                 let dst = interpolate_attribute("dst", element, runtime)?.map(PathBuf::from);
-                let dst = dst.or(parent.map(|group| group.dst));
+                let dst = dst.or(parent.and_then(|group| group.dst.clone()));
                 let tst = interpolate_attribute("tst", element, runtime)?.map(PathBuf::from);
-                let tst = tst.or(parent.map(|group| group.tst));
+                let tst = tst.or(parent.and_then(|group| group.tst.clone()));
+                let exclude = Exclusions::create(element, parent.map(|group| &group.exclude), runtime)?;
                 Ok(Group {
-                    src: src,
-                    dst: dst,
-                    tst: tst,
+                    src,
+                    dst,
+                    tst,
+                    exclude,
                 })
             }
         }"#;
@@ -231,10 +258,12 @@ mod test {
                 } else {
                     tst
                 };
+                let exclude = Exclusions::create(element, parent.map(|group| &group.exclude), runtime)?;
                 Ok(Group {
-                    src: src,
-                    dst: dst,
-                    tst: tst,
+                    src,
+                    dst,
+                    tst,
+                    exclude,
                 })
             }
         }"#;
@@ -267,10 +296,12 @@ mod test {
                 } else {
                     tst
                 };
+                let exclude = Exclusions::create(element, parent.map(|group| &group.exclude), runtime)?;
                 Ok(Group {
-                    src: src,
-                    dst: dst,
-                    tst: tst,
+                    src,
+                    dst,
+                    tst,
+                    exclude,
                 })
             }
         }"#;
@@ -286,7 +317,7 @@ mod test {
         impl Group {
             pub fn create(element: &Element, parent: Option<&Group>, runtime: &Runtime) -> Result<Group, Error> {
                 let src = interpolate_attribute("src", element, runtime)?.map(PathBuf::from);
-                let src = src.or(parent.map(|group| group.src));
+                let src = src.or(parent.and_then(|group| group.src.clone()));
                 let dst = interpolate_attribute("dst", element, runtime)?.map(PathBuf::from);
                 let dst = if let Some(group) = parent {
                     dst.apply_prefix(&group.dst)
@@ -299,10 +330,79 @@ mod test {
                 } else {
                     tst
                 };
+                let exclude = Exclusions::create(element, parent.map(|group| &group.exclude), runtime)?;
+                Ok(Group {
+                    src,
+                    dst,
+                    tst,
+                    exclude,
+                })
+            }
+        }"#;
+        test_utils::compare_impl(item, EXPECTED);
+    }
+
+    #[test]
+    fn group_impl_roles_apply_base() {
+        use GroupSetting::*;
+        let mut descriptor = test_descriptor(Prefix, InheritPrefix, Prefix);
+        descriptor.attributes[0].role = Some(ParameterRole::Source);
+        descriptor.attributes[1].role = Some(ParameterRole::Destination);
+        let item = super::generate_group_impl(&descriptor);
+        const EXPECTED: &str = r#"
+        impl Group {
+            pub fn create(element: &Element, parent: Option<&Group>, runtime: &Runtime) -> Result<Group, Error> {
+                let src = interpolate_attribute("src", element, runtime)?.map(PathBuf::from);
+                let src = if let Some(group) = parent {
+                    src.apply_prefix(&group.src)
+                } else {
+                    src.apply_prefix(&runtime.source_base)
+                };
+                let dst = interpolate_attribute("dst", element, runtime)?.map(PathBuf::from);
+                let dst = if let Some(group) = parent {
+                    dst.apply_prefix(&group.dst)
+                } else {
+                    dst.apply_prefix(&runtime.destination_base)
+                };
+                let tst = interpolate_attribute("tst", element, runtime)?.map(PathBuf::from);
+                let tst = if let Some(group) = parent {
+                    tst.apply_prefix(&group.tst)
+                } else {
+                    tst
+                };
+                let exclude = Exclusions::create(element, parent.map(|group| &group.exclude), runtime)?;
+                Ok(Group {
+                    src,
+                    dst,
+                    tst,
+                    exclude,
+                })
+            }
+        }"#;
+        test_utils::compare_impl(item, EXPECTED);
+    }
+
+    #[test]
+    fn group_impl_list_append() {
+        let descriptor = ElementDescriptor {
+            attributes: vec![new_parameter(
+                "includes",
+                ParameterType::List(Box::new(ParameterType::Path)),
+                false,
+                GroupSetting::Append,
+            )],
+            text: None,
+        };
+        let item = super::generate_group_impl(&descriptor);
+        const EXPECTED: &str = r#"
+        impl Group {
+            pub fn create(element: &Element, parent: Option<&Group>, runtime: &Runtime) -> Result<Group, Error> {
+                let includes = interpolate_list("includes", element, runtime)?.map(|values| values.into_iter().map(|value| Ok(PathBuf::from(value))).collect::<Result<Vec<_>, Error>>()).transpose()?;
+                let includes = append_list(parent.and_then(|group| group.includes.as_ref()), includes);
+                let exclude = Exclusions::create(element, parent.map(|group| &group.exclude), runtime)?;
                 Ok(Group {
-                    src: src,
-                    dst: dst,
-                    tst: tst,
+                    includes,
+                    exclude,
                 })
             }
         }"#;
@@ -1,11 +1,9 @@
 use codegen::{Block, Field, Function, Impl, Struct, Type};
 
-use crate::command::{ElementDescriptor, GroupSetting, ParameterDescriptor, ParameterType};
+use crate::command::{ElementDescriptor, GroupSetting, ParameterDescriptor, ParameterRole};
 
 fn generate_field_definition(parameter: &ParameterDescriptor) -> Field {
-    let field_type = match parameter.parameter_type {
-        ParameterType::Path => "PathBuf",
-    };
+    let field_type = parameter.parameter_type.rust_type();
     if parameter.required {
         Field::new(&parameter.name, field_type)
     } else {
@@ -15,7 +13,10 @@ fn generate_field_definition(parameter: &ParameterDescriptor) -> Field {
 }
 
 pub fn generate_item_definition(element_descriptor: &ElementDescriptor) -> Struct {
-    let mut struct_definition = Struct::new("Item").derive("Debug").to_owned();
+    let mut struct_definition = Struct::new("Item")
+        .derive("Debug")
+        .derive("Clone")
+        .to_owned();
     for attribute in &element_descriptor.attributes {
         let field = generate_field_definition(attribute);
         struct_definition.push_field(field);
@@ -23,21 +24,26 @@ pub fn generate_item_definition(element_descriptor: &ElementDescriptor) -> Struc
     struct_definition
 }
 
-fn add_parameter_code(function: &mut Function, parameter: &ParameterDescriptor) {
-    let conversion_suffix = match &parameter.parameter_type {
-        ParameterType::Path => ".map(PathBuf::from)",
+fn add_parameter_code(function: &mut Function, parameter: &ParameterDescriptor, from_text: bool) {
+    let conversion_suffix = parameter.parameter_type.conversion_suffix(&parameter.name);
+    let text_fallback = if from_text {
+        ".or(interpolate_text(element, runtime)?)"
+    } else {
+        ""
     };
     let init_line = format!(
-        r#"let {var_name} = interpolate_attribute("{var_name}", element, runtime)?{suffix};"#,
+        r#"let {var_name} = {reader}{fallback}{suffix};"#,
         var_name = parameter.name,
+        reader = parameter.parameter_type.reader(&parameter.name),
+        fallback = text_fallback,
         suffix = conversion_suffix
     );
     function.line(init_line);
     if let Some(attribute) = &parameter.defaults_to {
         let default_line = format!(
-            r#"let {var_name} = {var_name}.or(interpolate_attribute("{attribute}", element, runtime)?{suffix});"#,
+            r#"let {var_name} = {var_name}.or({reader}{suffix});"#,
             var_name = parameter.name,
-            attribute = attribute,
+            reader = parameter.parameter_type.reader(attribute),
             suffix = conversion_suffix
         );
         function.line(default_line);
@@ -45,28 +51,23 @@ fn add_parameter_code(function: &mut Function, parameter: &ParameterDescriptor)
     match parameter.allow_group {
         GroupSetting::None => {}
         GroupSetting::Inherit => {
+            let clone = if parameter.parameter_type.is_copy() {
+                ""
+            } else {
+                ".clone()"
+            };
             function.line(format!(
-                "let {var_name} = {var_name}.or(parent.{var_name});",
-                var_name = parameter.name
+                "let {var_name} = {var_name}.or(parent.{var_name}{clone});",
+                var_name = parameter.name,
+                clone = clone
             ));
         }
         GroupSetting::Prefix => {
-            let prefix = format!(
-                "let {var_name} = if let Some({var_name}) = {var_name}",
+            let line = format!(
+                "let {var_name} = {var_name}.apply_prefix_if_set(&parent.{var_name});",
                 var_name = parameter.name
             );
-            let if_block = Block::new(&prefix)
-                .line(format!(
-                    "Some({var_name}).apply_prefix(&parent.{var_name})",
-                    var_name = parameter.name
-                ))
-                .to_owned();
-            let else_block = Block::new("else")
-                .line(&parameter.name)
-                .after(";")
-                .to_owned();
-            function.push_block(if_block);
-            function.push_block(else_block);
+            function.line(line);
         }
         GroupSetting::InheritPrefix => {
             let line = format!(
@@ -75,6 +76,12 @@ fn add_parameter_code(function: &mut Function, parameter: &ParameterDescriptor)
             );
             function.line(line);
         }
+        GroupSetting::Append => {
+            function.line(format!(
+                "let {var_name} = append_list(parent.{var_name}.as_ref(), {var_name});",
+                var_name = parameter.name
+            ));
+        }
     }
     if parameter.required {
         function.line(format!(r#"let {var_name} = {var_name}.ok_or(Error::from("Missing required value: '{var_name}'"))?;"#, var_name = parameter.name));
@@ -91,20 +98,121 @@ pub fn generate_item_impl(element_descriptor: &ElementDescriptor) -> Impl {
         .to_owned();
     let mut constructor = Block::new("Ok(Item").after(")").to_owned();
     for attribute in &element_descriptor.attributes {
-        add_parameter_code(&mut create_function, attribute);
-        constructor.line(format!(
-            "{var_name}: {var_name},",
-            var_name = attribute.name
-        ));
+        let from_text = element_descriptor.text.as_ref() == Some(&attribute.name);
+        add_parameter_code(&mut create_function, attribute, from_text);
+        constructor.line(format!("{},", attribute.name));
     }
     create_function.push_block(constructor);
     Impl::new("Item").push_fn(create_function).to_owned()
 }
 
+pub fn generate_item_expand(element_descriptor: &ElementDescriptor) -> Function {
+    let find_role = |role: ParameterRole| {
+        element_descriptor
+            .attributes
+            .iter()
+            .find(|attribute| attribute.role == Some(role))
+    };
+    let mut expand_function = Function::new("expand")
+        .vis("pub")
+        .arg_self()
+        .ret(Type::new("Result<Vec<Item>, Error>"))
+        .to_owned();
+    let source = match find_role(ParameterRole::Source) {
+        Some(source) => source,
+        None => {
+            return expand_function
+                .arg("_element", Type::new("&Element"))
+                .arg("_parent", Type::new("&Group"))
+                .line("Ok(vec![self])")
+                .to_owned();
+        }
+    };
+    expand_function
+        .arg("element", Type::new("&Element"))
+        .arg("parent", Type::new("&Group"))
+        .line(format!(
+            "let root = Some(PathBuf::new()).apply_prefix(&parent.{}).unwrap_or_default();",
+            source.name
+        ));
+    if source.required {
+        expand_function.line(format!("let pattern = self.{}.clone();", source.name));
+    } else {
+        expand_function.push_block(
+            Block::new(&format!("let pattern = match &self.{}", source.name))
+                .line("Some(pattern) => pattern.clone(),")
+                .line("None => return Ok(vec![self]),")
+                .after(";")
+                .to_owned(),
+        );
+    }
+    let destination = find_role(ParameterRole::Destination);
+    let mut matches_block = Block::new(
+        "let matches = match expand_glob(&pattern, &root, glob_required(element)?, &parent.exclude)?",
+    );
+    matches_block.line("Some(matches) => matches,");
+    match destination {
+        Some(destination) => {
+            let resolve = if destination.required {
+                format!(
+                    "item.{var_name} = resolve_destination(&pattern, &item.{var_name});",
+                    var_name = destination.name
+                )
+            } else {
+                format!(
+                    "item.{var_name} = item.{var_name}.map(|{var_name}| resolve_destination(&pattern, &{var_name}));",
+                    var_name = destination.name
+                )
+            };
+            matches_block.push_block(
+                Block::new("None =>")
+                    .line("let mut item = self;")
+                    .line(resolve)
+                    .line("return Ok(vec![item]);")
+                    .to_owned(),
+            );
+        }
+        None => {
+            matches_block.line("None => return Ok(vec![self]),");
+        }
+    }
+    expand_function.push_block(matches_block.after(";").to_owned());
+    let mut map_block = Block::new("    .map(|(source, relative)|");
+    map_block.line("let mut item = self.clone();");
+    if source.required {
+        map_block.line(format!("item.{} = source;", source.name));
+    } else {
+        map_block.line(format!("item.{} = Some(source);", source.name));
+    }
+    if let Some(destination) = destination {
+        if destination.required {
+            map_block.line(format!(
+                "item.{var_name} = item.{var_name}.join(relative);",
+                var_name = destination.name
+            ));
+        } else {
+            map_block.line(format!(
+                "item.{var_name} = item.{var_name}.map(|{var_name}| {var_name}.join(relative));",
+                var_name = destination.name
+            ));
+        }
+    }
+    map_block.line("item").after(")");
+    expand_function
+        .line("let items = matches")
+        .line("    .into_iter()")
+        .push_block(map_block)
+        .line("    .collect();")
+        .line("Ok(items)")
+        .to_owned()
+}
+
 #[cfg(test)]
 mod test {
     use crate::{
-        command::{ElementDescriptor, GroupSetting, ParameterDescriptor, ParameterType},
+        command::{
+            ElementDescriptor, GroupSetting, ParameterDescriptor, ParameterRole, ParameterType,
+        },
         generator::test_utils,
     };
 
@@ -120,6 +228,7 @@ mod test {
             required: required,
             allow_group: allow_group,
             defaults_to: None,
+            role: None,
         }
     }
 
@@ -134,6 +243,7 @@ mod test {
                 new_parameter("dst", ParameterType::Path, setting2.1, setting2.0),
                 new_parameter("tst", ParameterType::Path, setting3.1, setting3.0),
             ],
+            text: None,
         }
     }
 
@@ -143,7 +253,7 @@ mod test {
         let descriptor = test_descriptor((None, true), (None, true), (None, true));
         let item = super::generate_item_definition(&descriptor);
         const EXPECTED: &str = r#"
-        #[derive(Debug)]
+        #[derive(Debug, Clone)]
         struct Item {
             src: PathBuf,
             dst: PathBuf,
@@ -158,7 +268,7 @@ mod test {
         let descriptor = test_descriptor((None, false), (None, false), (None, false));
         let item = super::generate_item_definition(&descriptor);
         const EXPECTED: &str = r#"
-        #[derive(Debug)]
+        #[derive(Debug, Clone)]
         struct Item {
             src: Option<PathBuf>,
             dst: Option<PathBuf>,
@@ -182,9 +292,9 @@ mod test {
                 let tst = interpolate_attribute("tst", element, runtime)?.map(PathBuf::from);
                 let tst = tst.ok_or(Error::from("Missing required value: 'tst'"))?;
                 Ok(Item {
-                    src: src,
-                    dst: dst,
-                    tst: tst,
+                    src,
+                    dst,
+                    tst,
                 })
             }
         }"#;
@@ -200,18 +310,18 @@ mod test {
         impl Item {
             pub fn create(element: &Element, parent: &Group, runtime: &Runtime) -> Result<Item, Error> {
                 let src = interpolate_attribute("src", element, runtime)?.map(PathBuf::from);
-                let src = src.or(parent.src);
+                let src = src.or(parent.src.clone());
                 let src = src.ok_or(Error::from("Missing required value: 'src'"))?;
                 let dst = interpolate_attribute("dst", element, runtime)?.map(PathBuf::from);
-                let dst = dst.or(parent.dst);
+                let dst = dst.or(parent.dst.clone());
                 let dst = dst.ok_or(Error::from("Missing required value: 'dst'"))?;
                 let tst = interpolate_attribute("tst", element, runtime)?.map(PathBuf::from);
-                let tst = tst.or(parent.tst);
+                let tst = tst.or(parent.tst.clone());
                 let tst = tst.ok_or(Error::from("Missing required value: 'tst'"))?;
                 Ok(Item {
-                    src: src,
-                    dst: dst,
-                    tst: tst,
+                    src,
+                    dst,
+                    tst,
                 })
             }
         }"#;
@@ -227,30 +337,18 @@ mod test {
         impl Item {
             pub fn create(element: &Element, parent: &Group, runtime: &Runtime) -> Result<Item, Error> {
                 let src = interpolate_attribute("src", element, runtime)?.map(PathBuf::from);
-                let src = if let Some(src) = src {
-                    Some(src).apply_prefix(&parent.src)
-                } else {
-                    src
-                };
+                let src = src.apply_prefix_if_set(&parent.src);
                 let src = src.ok_or(Error::from("Missing required value: 'src'"))?;
                 let dst = interpolate_attribute("dst", element, runtime)?.map(PathBuf::from);
-                let dst = if let Some(dst) = dst {
-                    Some(dst).apply_prefix(&parent.dst)
-                } else {
-                    dst
-                };
+                let dst = dst.apply_prefix_if_set(&parent.dst);
                 let dst = dst.ok_or(Error::from("Missing required value: 'dst'"))?;
                 let tst = interpolate_attribute("tst", element, runtime)?.map(PathBuf::from);
-                let tst = if let Some(tst) = tst {
-                    Some(tst).apply_prefix(&parent.tst)
-                } else {
-                    tst
-                };
+                let tst = tst.apply_prefix_if_set(&parent.tst);
                 let tst = tst.ok_or(Error::from("Missing required value: 'tst'"))?;
                 Ok(Item {
-                    src: src,
-                    dst: dst,
-                    tst: tst,
+                    src,
+                    dst,
+                    tst,
                 })
             }
         }"#;
@@ -267,31 +365,19 @@ mod test {
         impl Item {
             pub fn create(element: &Element, parent: &Group, runtime: &Runtime) -> Result<Item, Error> {
                 let src = interpolate_attribute("src", element, runtime)?.map(PathBuf::from);
-                let src = if let Some(src) = src {
-                    Some(src).apply_prefix(&parent.src)
-                } else {
-                    src
-                };
+                let src = src.apply_prefix_if_set(&parent.src);
                 let src = src.ok_or(Error::from("Missing required value: 'src'"))?;
                 let dst = interpolate_attribute("dst", element, runtime)?.map(PathBuf::from);
                 let dst = dst.or(interpolate_attribute("src", element, runtime)?.map(PathBuf::from));
-                let dst = if let Some(dst) = dst {
-                    Some(dst).apply_prefix(&parent.dst)
-                } else {
-                    dst
-                };
+                let dst = dst.apply_prefix_if_set(&parent.dst);
                 let dst = dst.ok_or(Error::from("Missing required value: 'dst'"))?;
                 let tst = interpolate_attribute("tst", element, runtime)?.map(PathBuf::from);
-                let tst = if let Some(tst) = tst {
-                    Some(tst).apply_prefix(&parent.tst)
-                } else {
-                    tst
-                };
+                let tst = tst.apply_prefix_if_set(&parent.tst);
                 let tst = tst.ok_or(Error::from("Missing required value: 'tst'"))?;
                 Ok(Item {
-                    src: src,
-                    dst: dst,
-                    tst: tst,
+                    src,
+                    dst,
+                    tst,
                 })
             }
         }"#;
@@ -320,9 +406,9 @@ mod test {
                 let tst = tst.apply_prefix(&parent.tst);
                 let tst = tst.ok_or(Error::from("Missing required value: 'tst'"))?;
                 Ok(Item {
-                    src: src,
-                    dst: dst,
-                    tst: tst,
+                    src,
+                    dst,
+                    tst,
                 })
             }
         }"#;
@@ -341,9 +427,9 @@ mod test {
                 let dst = interpolate_attribute("dst", element, runtime)?.map(PathBuf::from);
                 let tst = interpolate_attribute("tst", element, runtime)?.map(PathBuf::from);
                 Ok(Item {
-                    src: src,
-                    dst: dst,
-                    tst: tst,
+                    src,
+                    dst,
+                    tst,
                 })
             }
         }"#;
@@ -359,15 +445,15 @@ mod test {
         impl Item {
             pub fn create(element: &Element, parent: &Group, runtime: &Runtime) -> Result<Item, Error> {
                 let src = interpolate_attribute("src", element, runtime)?.map(PathBuf::from);
-                let src = src.or(parent.src);
+                let src = src.or(parent.src.clone());
                 let dst = interpolate_attribute("dst", element, runtime)?.map(PathBuf::from);
-                let dst = dst.or(parent.dst);
+                let dst = dst.or(parent.dst.clone());
                 let tst = interpolate_attribute("tst", element, runtime)?.map(PathBuf::from);
-                let tst = tst.or(parent.tst);
+                let tst = tst.or(parent.tst.clone());
                 Ok(Item {
-                    src: src,
-                    dst: dst,
-                    tst: tst,
+                    src,
+                    dst,
+                    tst,
                 })
             }
         }"#;
@@ -383,27 +469,15 @@ mod test {
         impl Item {
             pub fn create(element: &Element, parent: &Group, runtime: &Runtime) -> Result<Item, Error> {
                 let src = interpolate_attribute("src", element, runtime)?.map(PathBuf::from);
-                let src = if let Some(src) = src {
-                    Some(src).apply_prefix(&parent.src)
-                } else {
-                    src
-                };
+                let src = src.apply_prefix_if_set(&parent.src);
                 let dst = interpolate_attribute("dst", element, runtime)?.map(PathBuf::from);
-                let dst = if let Some(dst) = dst {
-                    Some(dst).apply_prefix(&parent.dst)
-                } else {
-                    dst
-                };
+                let dst = dst.apply_prefix_if_set(&parent.dst);
                 let tst = interpolate_attribute("tst", element, runtime)?.map(PathBuf::from);
-                let tst = if let Some(tst) = tst {
-                    Some(tst).apply_prefix(&parent.tst)
-                } else {
-                    tst
-                };
+                let tst = tst.apply_prefix_if_set(&parent.tst);
                 Ok(Item {
-                    src: src,
-                    dst: dst,
-                    tst: tst,
+                    src,
+                    dst,
+                    tst,
                 })
             }
         }"#;
@@ -429,12 +503,182 @@ mod test {
                 let tst = interpolate_attribute("tst", element, runtime)?.map(PathBuf::from);
                 let tst = tst.apply_prefix(&parent.tst);
                 Ok(Item {
-                    src: src,
-                    dst: dst,
-                    tst: tst,
+                    src,
+                    dst,
+                    tst,
+                })
+            }
+        }"#;
+        test_utils::compare_impl(item, EXPECTED);
+    }
+
+    #[test]
+    fn item_impl_bool_inherit() {
+        use GroupSetting::*;
+        let descriptor = ElementDescriptor {
+            attributes: vec![
+                new_parameter("flag", ParameterType::Bool, false, Inherit),
+                new_parameter("required_flag", ParameterType::Bool, true, None),
+            ],
+            text: Option::None,
+        };
+        let item = super::generate_item_impl(&descriptor);
+        const EXPECTED: &str = r#"
+        impl Item {
+            pub fn create(element: &Element, parent: &Group, runtime: &Runtime) -> Result<Item, Error> {
+                let flag = interpolate_attribute("flag", element, runtime)?.map(|value| parse_bool("flag", &value)).transpose()?;
+                let flag = flag.or(parent.flag);
+                let required_flag = interpolate_attribute("required_flag", element, runtime)?.map(|value| parse_bool("required_flag", &value)).transpose()?;
+                let required_flag = required_flag.ok_or(Error::from("Missing required value: 'required_flag'"))?;
+                Ok(Item {
+                    flag,
+                    required_flag,
                 })
             }
         }"#;
         test_utils::compare_impl(item, EXPECTED);
     }
+
+    #[test]
+    fn item_impl_list_append() {
+        use GroupSetting::*;
+        let descriptor = ElementDescriptor {
+            attributes: vec![
+                new_parameter(
+                    "defines",
+                    ParameterType::List(Box::new(ParameterType::String)),
+                    false,
+                    Append,
+                ),
+                new_parameter(
+                    "levels",
+                    ParameterType::List(Box::new(ParameterType::Int)),
+                    true,
+                    Inherit,
+                ),
+            ],
+            text: Option::None,
+        };
+        let item = super::generate_item_impl(&descriptor);
+        const EXPECTED: &str = r#"
+        impl Item {
+            pub fn create(element: &Element, parent: &Group, runtime: &Runtime) -> Result<Item, Error> {
+                let defines = interpolate_list("defines", element, runtime)?;
+                let defines = append_list(parent.defines.as_ref(), defines);
+                let levels = interpolate_list("levels", element, runtime)?.map(|values| values.into_iter().map(|value| parse_int("levels", &value)).collect::<Result<Vec<_>, Error>>()).transpose()?;
+                let levels = levels.or(parent.levels.clone());
+                let levels = levels.ok_or(Error::from("Missing required value: 'levels'"))?;
+                Ok(Item {
+                    defines,
+                    levels,
+                })
+            }
+        }"#;
+        test_utils::compare_impl(item, EXPECTED);
+    }
+
+    #[test]
+    fn item_impl_text() {
+        use GroupSetting::*;
+        let mut descriptor = test_descriptor((None, true), (None, false), (None, false));
+        descriptor.text = Some(String::from("src"));
+        let item = super::generate_item_impl(&descriptor);
+        const EXPECTED: &str = r#"
+        impl Item {
+            pub fn create(element: &Element, parent: &Group, runtime: &Runtime) -> Result<Item, Error> {
+                let src = interpolate_attribute("src", element, runtime)?.or(interpolate_text(element, runtime)?).map(PathBuf::from);
+                let src = src.ok_or(Error::from("Missing required value: 'src'"))?;
+                let dst = interpolate_attribute("dst", element, runtime)?.map(PathBuf::from);
+                let tst = interpolate_attribute("tst", element, runtime)?.map(PathBuf::from);
+                Ok(Item {
+                    src,
+                    dst,
+                    tst,
+                })
+            }
+        }"#;
+        test_utils::compare_impl(item, EXPECTED);
+    }
+
+    #[test]
+    fn item_expand_no_source() {
+        use GroupSetting::*;
+        let descriptor = test_descriptor((Prefix, true), (InheritPrefix, false), (None, false));
+        let item = super::generate_item_expand(&descriptor);
+        const EXPECTED: &str = r#"
+        pub fn expand(self, _element: &Element, _parent: &Group) -> Result<Vec<Item>, Error> {
+            Ok(vec![self])
+        }"#;
+        test_utils::compare_function(item, EXPECTED);
+    }
+
+    #[test]
+    fn item_expand_required_source() {
+        use GroupSetting::*;
+        let mut descriptor = test_descriptor((Prefix, true), (InheritPrefix, true), (None, false));
+        descriptor.attributes[0].role = Some(ParameterRole::Source);
+        descriptor.attributes[1].role = Some(ParameterRole::Destination);
+        let item = super::generate_item_expand(&descriptor);
+        const EXPECTED: &str = r#"
+        pub fn expand(self, element: &Element, parent: &Group) -> Result<Vec<Item>, Error> {
+            let root = Some(PathBuf::new()).apply_prefix(&parent.src).unwrap_or_default();
+            let pattern = self.src.clone();
+            let matches = match expand_glob(&pattern, &root, glob_required(element)?, &parent.exclude)? {
+                Some(matches) => matches,
+                None => {
+                    let mut item = self;
+                    item.dst = resolve_destination(&pattern, &item.dst);
+                    return Ok(vec![item]);
+                }
+            };
+            let items = matches
+                .into_iter()
+                .map(|(source, relative)| {
+                    let mut item = self.clone();
+                    item.src = source;
+                    item.dst = item.dst.join(relative);
+                    item
+                })
+                .collect();
+            Ok(items)
+        }"#;
+        test_utils::compare_function(item, EXPECTED);
+    }
+
+    #[test]
+    fn item_expand_optional_source() {
+        use GroupSetting::*;
+        let mut descriptor =
+            test_descriptor((Prefix, false), (InheritPrefix, false), (None, false));
+        descriptor.attributes[0].role = Some(ParameterRole::Source);
+        descriptor.attributes[1].role = Some(ParameterRole::Destination);
+        let item = super::generate_item_expand(&descriptor);
+        const EXPECTED: &str = r#"
+        pub fn expand(self, element: &Element, parent: &Group) -> Result<Vec<Item>, Error> {
+            let root = Some(PathBuf::new()).apply_prefix(&parent.src).unwrap_or_default();
+            let pattern = match &self.src {
+                Some(pattern) => pattern.clone(),
+                None => return Ok(vec![self]),
+            };
+            let matches = match expand_glob(&pattern, &root, glob_required(element)?, &parent.exclude)? {
+                Some(matches) => matches,
+                None => {
+                    let mut item = self;
+                    item.dst = item.dst.map(|dst| resolve_destination(&pattern, &dst));
+                    return Ok(vec![item]);
+                }
+            };
+            let items = matches
+                .into_iter()
+                .map(|(source, relative)| {
+                    let mut item = self.clone();
+                    item.src = Some(source);
+                    item.dst = item.dst.map(|dst| dst.join(relative));
+                    item
+                })
+                .collect();
+            Ok(items)
+        }"#;
+        test_utils::compare_function(item, EXPECTED);
+    }
 }
@@ -1,5 +1,6 @@
 mod group;
 mod item;
+mod schema;
 #[cfg(test)]
 mod test_utils;
 
@@ -8,14 +9,21 @@ use codegen::{Impl, Variant};
 use convert_case::{Case, Casing};
 use regex::Regex;
 
+use std::path::PathBuf;
+
 pub use group::generate_group_definition;
 pub use group::generate_group_impl;
 pub use item::generate_item_definition;
+pub use item::generate_item_expand;
 pub use item::generate_item_impl;
+pub use schema::generate_schema;
 
-use crate::command::{Command, CommandLineDescriptor, TaskDescriptor};
+use crate::command::{
+    Command, CommandLineDescriptor, ElementDescriptor, GroupSetting, ParameterDescriptor,
+    ParameterRole, ParameterType, SnippetDescriptor, TaskDescriptor,
+};
 
-use super::command_parser::CommandPart;
+use super::command_parser::{CommandDetails, CommandPart};
 
 macro_rules! t {
     ($ty:literal) => {
@@ -24,58 +32,73 @@ macro_rules! t {
 }
 
 pub fn generate_parse_item() -> Function {
-    let if_block = Block::new("let item = if condition")
+    let if_block = Block::new("let items = if condition")
         .line("let item = Item::create(element, parent, runtime)?;")
-        .line("Some(item)")
+        .line("item.expand(element, parent)?")
         .to_owned();
-    let else_block = Block::new("else").line("None").after(";").to_owned();
+    let else_block = Block::new("else").line("Vec::new()").after(";").to_owned();
     Function::new("parse_item")
         .arg("runtime", t!("&Runtime"))
         .arg("element", t!("&Element"))
         .arg("parent", t!("&Group"))
-        .ret(t!("Result<Option<Item>, Error>"))
+        .ret(t!("Result<Vec<Item>, Error>"))
         .line("let condition = evaluate_condition_from_element(runtime, element)?;")
         .push_block(if_block)
         .push_block(else_block)
-        .line("Ok(item)")
+        .line("Ok(items)")
         .to_owned()
 }
 
-fn generate_parse_items_loop() -> Block {
+fn generate_parse_items_loop(element: &ElementDescriptor) -> Block {
     let item_arm = Block::new("\"item\" => ")
-        .line("let item = parse_item(runtime, item, &group)?;")
+        .line("let result = parse_item(runtime, item, &group);")
         .push_block(
-            Block::new("if let Some(item) = item")
-                .line("items.push(item);")
+            Block::new("if let Some(mut inner_items) = diagnostics.record(item, result)")
+                .line("items.append(&mut inner_items);")
                 .to_owned(),
         )
         .to_owned();
     let group_arm = Block::new("\"group\" => ")
-        .line("let inner_items = parse_items(runtime, item, Some(&group))?;")
+        .line("let result = parse_items(runtime, item, Some(&group), diagnostics);")
         .push_block(
-            Block::new("if let Some(mut inner_items) = inner_items")
+            Block::new("if let Some(Some(mut inner_items)) = diagnostics.record(item, result)")
                 .line("items.append(&mut inner_items);")
                 .to_owned(),
         )
         .to_owned();
+    let exclude_arm = Block::new("\"exclude\" => ").to_owned();
+    let list_arms: Vec<Block> = element
+        .attributes
+        .iter()
+        .filter(|attribute| {
+            attribute.parameter_type.is_list()
+                && !matches!(attribute.allow_group, GroupSetting::None)
+        })
+        .map(|attribute| Block::new(&format!("\"{}\" => ", attribute.name)))
+        .collect();
     let catch_all_arm = Block::new("_ =>")
-        .line("Err(internal_error!(\"Invalid element: {}\", item.name()))?;")
+        .line("let error = internal_error!(\"Invalid element: {}\", item.name());")
+        .line("diagnostics.record::<()>(item, Err(error));")
         .to_owned();
-    let match_block = Block::new("match item.name()")
+    let mut match_block = Block::new("match item.name()")
         .push_block(item_arm)
         .push_block(group_arm)
-        .push_block(catch_all_arm)
+        .push_block(exclude_arm)
         .to_owned();
+    for list_arm in list_arms {
+        match_block.push_block(list_arm);
+    }
+    match_block.push_block(catch_all_arm);
     Block::new("for item in parent.children()")
         .push_block(match_block)
         .to_owned()
 }
 
-pub fn generate_parse_items() -> Function {
+pub fn generate_parse_items(element: &ElementDescriptor) -> Function {
     let if_block = Block::new("let items = if condition")
         .line("let group = Group::create(parent, group, runtime)?;")
         .line("let mut items = Vec::new();")
-        .push_block(generate_parse_items_loop())
+        .push_block(generate_parse_items_loop(element))
         .line("Some(items)")
         .to_owned();
     let else_block = Block::new("else").line("None").after(";").to_owned();
@@ -83,6 +106,7 @@ pub fn generate_parse_items() -> Function {
         .arg("runtime", t!("&Runtime"))
         .arg("parent", t!("&Element"))
         .arg("group", t!("Option<&Group>"))
+        .arg("diagnostics", t!("&mut Diagnostics"))
         .ret(t!("Result<Option<Vec<Item>>, Error>"))
         .line("let condition = evaluate_condition_from_element(runtime, parent)?;")
         .push_block(if_block)
@@ -91,18 +115,48 @@ pub fn generate_parse_items() -> Function {
         .to_owned()
 }
 
-pub fn generate_task_struct() -> Struct {
-    Struct::new("Task")
+pub fn generate_task_struct(task: &TaskDescriptor) -> Struct {
+    let mut task_struct = Struct::new("Task")
         .derive("Debug")
         .vis("pub")
         .field("items", t!("Vec<Item>"))
-        .to_owned()
+        .to_owned();
+    if let Command::CommandLine(_) = task.command {
+        task_struct.field("variant", t!("usize"));
+    }
+    task_struct
+}
+
+fn generate_role_reference(task: &TaskDescriptor, role: ParameterRole) -> String {
+    let parameter = task
+        .element
+        .attributes
+        .iter()
+        .find(|attribute| attribute.role == Some(role));
+    match parameter {
+        Some(parameter) if parameter.required => format!("Some(&item.{})", parameter.name),
+        Some(parameter) => format!("item.{}.as_ref()", parameter.name),
+        None => String::from("None"),
+    }
 }
 
 pub fn generate_task_impl(task: &TaskDescriptor) -> Impl {
     let snake_name = task.name.to_case(Case::Snake);
-    let for_block = Block::new("for item in &self.items")
-        .line(format!("{}(item)?;", snake_name))
+    let context = format!(
+        "ErrorContext::new(\"{}\", index, {}, {})",
+        snake_name,
+        generate_role_reference(task, ParameterRole::Source),
+        generate_role_reference(task, ParameterRole::Destination)
+    );
+    let (arguments, describe) = match task.command {
+        Command::CommandLine(_) => ("item, self.variant", "|item| describe(item, self.variant)"),
+        Command::Snippet(_) => ("item", "describe"),
+    };
+    let for_block = Block::new("for (index, item) in self.items.iter().enumerate()")
+        .line(format!(
+            "{}({}).map_err(|error| error.with_context({}))?;",
+            snake_name, arguments, context
+        ))
         .to_owned();
     let run_fn = Function::new("run")
         .vis("pub")
@@ -111,10 +165,98 @@ pub fn generate_task_impl(task: &TaskDescriptor) -> Impl {
         .push_block(for_block)
         .line("Ok(())")
         .to_owned();
-    Impl::new("Task").push_fn(run_fn).to_owned()
+    let describe_fn = Function::new("describe")
+        .vis("pub")
+        .arg_ref_self()
+        .ret(t!("Vec<String>"))
+        .line(format!("self.items.iter().map({}).collect()", describe))
+        .to_owned();
+    let validate_fn = Function::new("validate")
+        .vis("pub")
+        .arg_ref_self()
+        .arg("produced", t!("&mut Vec<PathBuf>"))
+        .ret(t!("Vec<Error>"))
+        .line("self.items")
+        .line("    .iter()")
+        .line("    .enumerate()")
+        .line(format!(
+            "    .filter_map(|(index, item)| check_item({}, produced))",
+            context
+        ))
+        .line("    .collect()")
+        .to_owned();
+    Impl::new("Task")
+        .push_fn(run_fn)
+        .push_fn(describe_fn)
+        .push_fn(validate_fn)
+        .to_owned()
+}
+
+fn command_argument(element: &ElementDescriptor, name: &str, value: &str, bound: bool) -> String {
+    let parameter_type = element
+        .attributes
+        .iter()
+        .find(|attribute| attribute.name == name)
+        .map(|attribute| &attribute.parameter_type)
+        .unwrap_or(&ParameterType::Path);
+    if bound {
+        parameter_type.bound_argument(value)
+    } else {
+        parameter_type.argument(value)
+    }
+}
+
+fn find_list<'a>(element: &'a ElementDescriptor, name: &str) -> Option<&'a ParameterDescriptor> {
+    element
+        .attributes
+        .iter()
+        .find(|attribute| attribute.name == name && attribute.parameter_type.is_list())
+}
+
+/// Passes every value of a list parameter outside of optional parts.
+fn list_arguments(list: &ParameterDescriptor) -> String {
+    let values = if list.required {
+        format!("item.{}.iter()", list.name)
+    } else {
+        format!("item.{}.iter().flatten()", list.name)
+    };
+    match &list.parameter_type {
+        ParameterType::List(inner) if !inner.is_copy() => format!("call.args({});", values),
+        _ => format!("call.args({}.map(|value| value.to_string()));", values),
+    }
+}
+
+/// Functions and blocks both hold the statements building a command line.
+trait Body {
+    fn line(&mut self, line: String);
+    fn push_block(&mut self, block: Block);
+}
+
+impl Body for Function {
+    fn line(&mut self, line: String) {
+        Function::line(self, line);
+    }
+
+    fn push_block(&mut self, block: Block) {
+        Function::push_block(self, block);
+    }
 }
 
-fn add_command_part_handling(function: &mut Function, part: &CommandPart) {
+impl Body for Block {
+    fn line(&mut self, line: String) {
+        Block::line(self, line);
+    }
+
+    fn push_block(&mut self, block: Block) {
+        Block::push_block(self, block);
+    }
+}
+
+fn add_command_part_handling(
+    function: &mut impl Body,
+    part: &CommandPart,
+    element: &ElementDescriptor,
+) {
     if part.optional {
         let lhs = part
             .dependencies
@@ -133,19 +275,52 @@ fn add_command_part_handling(function: &mut Function, part: &CommandPart) {
         } else {
             format!("if let {} = {}", lhs, rhs)
         };
+        let list = part
+            .dependencies
+            .iter()
+            .find_map(|dependency| find_list(element, dependency));
         let mut block = Block::new(&if_stmt);
+        let mut arguments = Vec::new();
         for token in &part.tokens {
             if token.starts_with("$") {
-                block.line(format!("call.arg(&{});", &token[1..]));
+                let argument = match list {
+                    Some(list) if list.name == token[1..] => match &list.parameter_type {
+                        ParameterType::List(inner) => inner.bound_argument("value"),
+                        _ => unreachable!(),
+                    },
+                    _ => command_argument(element, &token[1..], &token[1..], true),
+                };
+                arguments.push(format!("call.arg({});", argument));
             } else {
-                block.line(format!("call.arg(\"{}\");", &token));
+                arguments.push(format!("call.arg(\"{}\");", &token));
             };
         }
+        match list {
+            Some(list) => {
+                let mut for_block = Block::new(&format!("for value in {}", list.name));
+                for argument in arguments {
+                    for_block.line(argument);
+                }
+                block.push_block(for_block);
+            }
+            None => {
+                for argument in arguments {
+                    block.line(argument);
+                }
+            }
+        }
         function.push_block(block);
     } else {
         for token in &part.tokens {
-            if token.starts_with("$") {
-                function.line(format!("call.arg(&item.{});", &token[1..]));
+            if let Some(list) = token
+                .strip_prefix('$')
+                .and_then(|name| find_list(element, name))
+            {
+                function.line(list_arguments(list));
+            } else if let Some(name) = token.strip_prefix('$') {
+                let value = format!("item.{}", name);
+                let argument = command_argument(element, name, &value, false);
+                function.line(format!("call.arg({});", argument));
             } else {
                 function.line(format!("call.arg(\"{}\");", &token));
             };
@@ -153,72 +328,237 @@ fn add_command_part_handling(function: &mut Function, part: &CommandPart) {
     }
 }
 
-fn generate_command_line_execute(
+fn quoted_key(key: &Option<String>) -> String {
+    match key {
+        Some(key) => format!("Some(\"{}\")", escape_literal(key)),
+        None => String::from("None"),
+    }
+}
+
+pub fn generate_select_variant_fn(
+    task: &TaskDescriptor,
     descriptor: &CommandLineDescriptor,
-    mut function: Function,
 ) -> Function {
-    let command = if cfg!(target_os = "windows") {
-        &descriptor.windows
-    } else if cfg!(target_os = "linux") {
-        &descriptor.linux
-    } else if cfg!(target_os = "macos") {
-        &descriptor.macos
-    } else {
-        panic!("Unsupported OS")
-    };
-    function.line("use std::process::Command;");
-    function.line(format!(
+    let variants = descriptor
+        .variants
+        .iter()
+        .map(|variant| {
+            format!(
+                "({}, {}, {})",
+                quoted_key(&variant.os),
+                quoted_key(&variant.arch),
+                quoted_key(&variant.condition)
+            )
+        })
+        .collect::<Vec<String>>()
+        .join(", ");
+    Function::new("select_variant")
+        .arg("runtime", t!("&Runtime"))
+        .ret(t!("Result<usize, Error>"))
+        .line(format!(
+            "select_command_variant(\"{}\", runtime, &[{}], {})",
+            task.name.to_case(Case::Snake),
+            variants,
+            descriptor.default.is_some()
+        ))
+        .to_owned()
+}
+
+fn generate_command_line_body(
+    body: &mut impl Body,
+    command: &CommandDetails,
+    element: &ElementDescriptor,
+) {
+    body.line(format!(
         "let mut call = Command::new(\"{}\");",
         &command.command_name
     ));
-
     for part in &command.parts {
-        add_command_part_handling(&mut function, part);
+        add_command_part_handling(body, part, element);
+    }
+    body.line(String::from("call"));
+}
+
+pub fn generate_command_line_fn(
+    descriptor: &CommandLineDescriptor,
+    element: &ElementDescriptor,
+) -> Function {
+    let commands = descriptor.commands().collect::<Vec<&CommandDetails>>();
+    let mut function = Function::new("command_line")
+        .arg("item", t!("&Item"))
+        .to_owned();
+    if let [command] = commands[..] {
+        function.arg("_variant", t!("usize")).ret(t!("Command"));
+        generate_command_line_body(&mut function, command, element);
+    } else {
+        let mut match_block = Block::new("match variant");
+        for (index, command) in commands.iter().enumerate() {
+            let pattern = if index + 1 == commands.len() {
+                String::from("_ =>")
+            } else {
+                format!("{} =>", index)
+            };
+            let mut arm = Block::new(&pattern);
+            generate_command_line_body(&mut arm, command, element);
+            match_block.push_block(arm);
+        }
+        function
+            .arg("variant", t!("usize"))
+            .ret(t!("Command"))
+            .push_block(match_block);
     }
+    function
+}
 
+fn generate_command_line_execute(mut function: Function) -> Function {
     function
-        .line("let output = call.output()?;")
+        .arg("variant", t!("usize"))
+        .line("let output = command_line(item, variant).output()?;")
         .line("let status = output.status;")
         .push_block(Block::new("if status.success()").line("Ok(())").to_owned())
         .push_block(
             Block::new("else")
                 .line("let std_err = std::str::from_utf8(&output.stderr)?;")
-                .line("Err(Error::from(std_err))")
+                .line("Err(Error::from(std_err.trim()))")
                 .to_owned(),
         )
         .to_owned()
 }
 
-pub fn generate_execute_fn(task: &TaskDescriptor) -> Function {
+fn generate_snippet_execute(mut function: Function, code: &str) -> Function {
+    let re = Regex::new(r"\$\{(?P<var>[a-z][a-z0-9_]*)\}").unwrap();
+    let output = re.replace_all(code, "&item.$var");
+    function.line(&output).to_owned()
+}
+
+/// One function per snippet variant, each compiled only for the targets that
+/// match it and none of the variants before it. Without a default, other
+/// targets get a function that fails.
+fn generate_snippet_variants(
+    task: &TaskDescriptor,
+    function: Function,
+    snippet: &SnippetDescriptor,
+) -> Vec<Function> {
+    let mut functions = Vec::new();
+    let mut previous: Vec<String> = Vec::new();
+    for variant in &snippet.variants {
+        let cfg = if previous.is_empty() {
+            variant.cfg()
+        } else {
+            format!("all({}, not(any({})))", variant.cfg(), previous.join(", "))
+        };
+        let mut variant_fn = generate_snippet_execute(function.clone(), &variant.code);
+        functions.push(variant_fn.attr(&format!("cfg({})", cfg)).to_owned());
+        previous.push(variant.cfg());
+    }
+    let mut fallback = match &snippet.default {
+        Some(code) => generate_snippet_execute(function, code),
+        None => Function::new(&task.name.to_case(Case::Snake))
+            .arg("_item", t!("&Item"))
+            .ret(t!("Result<(), Error>"))
+            .line(format!(
+                "Err(internal_error!(\"Task {} is not supported on this platform\"))",
+                task.name
+            ))
+            .to_owned(),
+    };
+    if !previous.is_empty() {
+        fallback.attr(&format!("cfg(not(any({})))", previous.join(", ")));
+    }
+    functions.push(fallback);
+    functions
+}
+
+pub fn generate_execute_fns(task: &TaskDescriptor) -> Vec<Function> {
     let snake_name = task.name.to_case(Case::Snake);
-    let mut execute_fn = Function::new(&snake_name)
+    let execute_fn = Function::new(&snake_name)
         .arg("item", t!("&Item"))
         .ret(t!("Result<(), Error>"))
         .to_owned();
     match &task.command {
-        Command::Snippet(code) => {
-            let re = Regex::new(r"\$\{(?P<var>[a-z][a-z0-9_]*)\}").unwrap();
-            let output = re.replace_all(&code, "&item.$var");
-            execute_fn.line(&output).to_owned()
-        }
-        Command::CommandLine(command_line) => {
-            generate_command_line_execute(&command_line, execute_fn)
-        }
+        Command::Snippet(snippet) => generate_snippet_variants(task, execute_fn, snippet),
+        Command::CommandLine(_) => vec![generate_command_line_execute(execute_fn)],
+    }
+}
+
+fn escape_literal(literal: &str) -> String {
+    literal.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn generate_description_format(template: &str) -> String {
+    let re = Regex::new(r"\$\{(?P<var>[a-z][a-z0-9_]*)\}").unwrap();
+    let escape_braces = |literal: &str| literal.replace('{', "{{").replace('}', "}}");
+    let mut format_string = String::new();
+    let mut arguments = Vec::new();
+    let mut last = 0;
+    for capture in re.captures_iter(template) {
+        let whole = capture.get(0).unwrap();
+        format_string.push_str(&escape_braces(&template[last..whole.start()]));
+        format_string.push_str("{}");
+        arguments.push(format!("item.{}.describe()", &capture["var"]));
+        last = whole.end();
+    }
+    format_string.push_str(&escape_braces(&template[last..]));
+    if arguments.is_empty() {
+        format!("String::from(\"{}\")", escape_literal(template))
+    } else {
+        format!(
+            "format!(\"{}\", {})",
+            escape_literal(&format_string),
+            arguments.join(", ")
+        )
     }
 }
 
-pub fn generate_parse_task() -> Function {
-    let constructor = Block::new("Task").line("items: items").to_owned();
-    let map_call = Block::new("let task = items.map(|items|")
-        .push_block(constructor)
-        .after(");")
+pub fn generate_describe_fn(task: &TaskDescriptor) -> Function {
+    let mut describe_fn = Function::new("describe")
+        .arg("item", t!("&Item"))
+        .ret(t!("String"))
         .to_owned();
+    match (&task.command, &task.description) {
+        (Command::CommandLine(_), _) => describe_fn
+            .arg("variant", t!("usize"))
+            .line("format!(\"{:?}\", command_line(item, variant))"),
+        (Command::Snippet(_), Some(template)) => {
+            describe_fn.line(generate_description_format(template))
+        }
+        (Command::Snippet(_), None) => describe_fn.line(format!(
+            "format!(\"{} {{:?}}\", item)",
+            task.name.to_case(Case::Snake)
+        )),
+    };
+    describe_fn
+}
+
+pub fn generate_parse_task(task: &TaskDescriptor) -> Function {
+    let map_call = match task.command {
+        Command::CommandLine(_) => {
+            let constructor = Block::new("Some(items) => Some(Task")
+                .line("items,")
+                .line("variant: select_variant(runtime)?,")
+                .after("),")
+                .to_owned();
+            Block::new("let task = match items")
+                .push_block(constructor)
+                .line("None => None,")
+                .after(";")
+                .to_owned()
+        }
+        Command::Snippet(_) => {
+            let constructor = Block::new("Task").line("items").to_owned();
+            Block::new("let task = items.map(|items|")
+                .push_block(constructor)
+                .after(");")
+                .to_owned()
+        }
+    };
     Function::new("parse_task")
         .vis("pub")
         .arg("runtime", t!("&Runtime"))
         .arg("parent", t!("&Element"))
+        .arg("diagnostics", t!("&mut Diagnostics"))
         .ret(t!("Result<Option<Task>, Error>"))
-        .line("let items = parse_items(runtime, parent, None)?;")
+        .line("let items = parse_items(runtime, parent, None, diagnostics)?;")
         .push_block(map_call)
         .line("Ok(task)")
         .to_owned()
@@ -237,70 +577,121 @@ pub fn generate_task_enum(tasks: &Vec<TaskDescriptor>) -> Enum {
         let variant = generate_variant(&task);
         enum_definition.push_variant(variant);
     }
+    enum_definition.push_variant(Variant::new("Dynamic").tuple("DynamicTask").to_owned());
+    enum_definition.push_variant(Variant::new("Skipped").tuple("SkippedTask").to_owned());
     enum_definition
 }
 
-pub fn generate_task_enum_impl(tasks: &Vec<TaskDescriptor>) -> Impl {
+fn generate_task_enum_dispatch(tasks: &Vec<TaskDescriptor>, call: &str) -> Block {
     let mut match_block = Block::new("match &self");
     for task in tasks {
         let snake_name = task.name.to_case(Case::Snake);
         let pascal_name = task.name.to_case(Case::Pascal);
         match_block.line(format!(
-            "Task::{pascal}({snake}) => {snake}.run(),",
+            "Task::{pascal}({snake}) => {snake}.{call},",
             snake = snake_name,
-            pascal = pascal_name
+            pascal = pascal_name,
+            call = call
         ));
     }
+    match_block.line(format!("Task::Dynamic(dynamic) => dynamic.{},", call));
+    match_block.line(format!("Task::Skipped(skipped) => skipped.{},", call));
+    match_block
+}
+
+pub fn generate_builtin_tasks(tasks: &[TaskDescriptor]) -> String {
+    let names = tasks
+        .iter()
+        .map(|task| format!("\"{}\"", task.name.to_case(Case::Snake)))
+        .collect::<Vec<String>>()
+        .join(", ");
+    format!("pub const BUILTIN_TASKS: &[&str] = &[{}];", names)
+}
+
+/// Embeds the built-in descriptors, so `bf` can describe them along with the
+/// ones it loads at runtime.
+pub fn generate_builtin_descriptors(paths: &[PathBuf]) -> String {
+    let descriptors = paths
+        .iter()
+        .map(|path| format!("include_str!({:?})", path))
+        .collect::<Vec<String>>()
+        .join(", ");
+    format!(
+        "pub const BUILTIN_DESCRIPTORS: &[&str] = &[{}];",
+        descriptors
+    )
+}
+
+pub fn generate_task_enum_impl(tasks: &Vec<TaskDescriptor>) -> Impl {
     let run_fn = Function::new("run")
         .vis("pub")
         .arg_ref_self()
         .ret(t!("Result<(), Error>"))
-        .push_block(match_block)
+        .push_block(generate_task_enum_dispatch(tasks, "run()"))
+        .to_owned();
+    let describe_fn = Function::new("describe")
+        .vis("pub")
+        .arg_ref_self()
+        .ret(t!("Vec<String>"))
+        .push_block(generate_task_enum_dispatch(tasks, "describe()"))
         .to_owned();
-    Impl::new("Task").push_fn(run_fn).to_owned()
+    let validate_fn = Function::new("validate")
+        .vis("pub")
+        .arg_ref_self()
+        .arg("produced", t!("&mut Vec<PathBuf>"))
+        .ret(t!("Vec<Error>"))
+        .push_block(generate_task_enum_dispatch(tasks, "validate(produced)"))
+        .to_owned();
+    Impl::new("Task")
+        .push_fn(run_fn)
+        .push_fn(describe_fn)
+        .push_fn(validate_fn)
+        .to_owned()
 }
 
 fn generate_parse_input_match(tasks: &Vec<TaskDescriptor>) -> Block {
-    let mut match_block = Block::new("match task_name");
+    let mut match_block = Block::new("let result = match task_name");
     for task in tasks {
         let name_snake = task.name.to_case(Case::Snake);
         let name_pascal = task.name.to_case(Case::Pascal);
-        let block = Block::new(&format!("\"{}\" =>", &name_snake))
-            .line(format!(
-                "let task = {}::parse_task(runtime, task)?.map(|task| Task::{}(task));",
-                &name_snake, &name_pascal
-            ))
-            .line("Ok(task)")
-            .after(",")
-            .to_owned();
-        match_block.push_block(block);
+        match_block.line(format!(
+            "\"{}\" => {}::parse_task(runtime, element, diagnostics).map(|task| task.map(Task::{})),",
+            &name_snake, &name_snake, &name_pascal
+        ));
     }
     match_block
-        .line("_ => Err(Error::from(format!(\"Invalid task '{}'\", task_name))),")
+        .line("_ => dynamic::parse_task(runtime, element, diagnostics).map(|task| task.map(Task::Dynamic)),")
+        .after(";")
         .to_owned()
 }
 
 pub fn generate_parse_input(tasks: &Vec<TaskDescriptor>) -> Function {
-    let map_block = Block::new("    .map(|task|")
-        .line("let task_name = task.name();")
-        .push_block(generate_parse_input_match(tasks))
-        .after(")")
+    let skipped_arm = Block::new("Some(None) =>")
+        .line("let skipped = SkippedTask::new(element, runtime, diagnostics.location(element));")
+        .push_block(
+            Block::new("if let Some(skipped) = diagnostics.record(element, skipped)")
+                .line("tasks.push(Task::Skipped(skipped));")
+                .to_owned(),
+        )
         .to_owned();
-    let filter_map_block = Block::new("    .filter_map(|x| match x")
-        .line("Ok(task) => task.map(|task| Ok(task)),")
-        .line("Err(err) => Some(Err(err)),")
-        .after(")")
+    let record_block = Block::new("match diagnostics.record(element, result)")
+        .line("Some(Some(task)) => tasks.push(task),")
+        .push_block(skipped_arm)
+        .line("None => {}")
+        .to_owned();
+    let for_block = Block::new("for element in root.children()")
+        .line("let task_name = element.name();")
+        .push_block(generate_parse_input_match(tasks))
+        .push_block(record_block)
         .to_owned();
     let parse_input = Function::new("parse_input")
         .arg("runtime", t!("&Runtime"))
-        .arg("input", t!("&str"))
-        .ret(t!("Result<Vec<Task>, Error>"))
-        .line("let xml_elements: Element = input.parse()?;")
-        .line("xml_elements")
-        .line("    .children()")
-        .push_block(map_block)
-        .push_block(filter_map_block)
-        .line("    .collect()")
+        .arg("root", t!("&Element"))
+        .arg("diagnostics", t!("&mut Diagnostics"))
+        .ret(t!("Vec<Task>"))
+        .line("let mut tasks = Vec::new();")
+        .push_block(for_block)
+        .line("tasks")
         .to_owned();
     parse_input
 }
@@ -308,20 +699,29 @@ pub fn generate_parse_input(tasks: &Vec<TaskDescriptor>) -> Function {
 #[cfg(test)]
 mod test {
     use crate::{
-        command::{Command, ElementDescriptor, TaskDescriptor},
-        generator::{generate_parse_task, test_utils},
+        command::{
+            Command, CommandLineDescriptor, CommandVariant, ElementDescriptor, GroupSetting,
+            ParameterDescriptor, ParameterRole, ParameterType, SnippetDescriptor, TaskDescriptor,
+        },
+        command_parser::CommandDetails,
+        generator::{generate_parse_task, generate_select_variant_fn, test_utils},
     };
 
     use super::{
-        generate_parse_input, generate_parse_item, generate_parse_items, generate_task_enum,
-        generate_task_enum_impl, generate_task_impl, generate_task_struct,
+        generate_command_line_fn, generate_describe_fn, generate_execute_fns, generate_parse_input,
+        generate_parse_item, generate_parse_items, generate_task_enum, generate_task_enum_impl,
+        generate_task_impl, generate_task_struct,
     };
 
     fn mock_task(name: &str) -> TaskDescriptor {
         TaskDescriptor {
             name: String::from(name),
-            command: Command::Snippet(String::from("asdf")),
-            element: ElementDescriptor { attributes: vec![] },
+            description: None,
+            command: Command::Snippet(SnippetDescriptor::from("asdf")),
+            element: ElementDescriptor {
+                attributes: vec![],
+                text: None,
+            },
         }
     }
 
@@ -329,15 +729,15 @@ mod test {
     fn parse_item() {
         let item = generate_parse_item();
         const EXPECTED: &str = r#"
-        fn parse_item(runtime: &Runtime, element: &Element, parent: &Group) -> Result<Option<Item>, Error> {
+        fn parse_item(runtime: &Runtime, element: &Element, parent: &Group) -> Result<Vec<Item>, Error> {
             let condition = evaluate_condition_from_element(runtime, element)?;
-            let item = if condition {
+            let items = if condition {
                 let item = Item::create(element, parent, runtime)?;
-                Some(item)
+                item.expand(element, parent)?
             } else {
-                None
+                Vec::new()
             };
-            Ok(item)
+            Ok(items)
         }
         "#;
         test_utils::compare_function(item, EXPECTED);
@@ -345,9 +745,9 @@ mod test {
 
     #[test]
     fn parse_items() {
-        let item = generate_parse_items();
+        let item = generate_parse_items(&mock_task("copy").element);
         const EXPECTED: &str = r#"
-        fn parse_items(runtime: &Runtime, parent: &Element, group: Option<&Group>) -> Result<Option<Vec<Item>>, Error> {
+        fn parse_items(runtime: &Runtime, parent: &Element, group: Option<&Group>, diagnostics: &mut Diagnostics) -> Result<Option<Vec<Item>>, Error> {
             let condition = evaluate_condition_from_element(runtime, parent)?;
             let items = if condition {
                 let group = Group::create(parent, group, runtime)?;
@@ -355,19 +755,22 @@ mod test {
                 for item in parent.children() {
                     match item.name() {
                         "item" => {
-                            let item = parse_item(runtime, item, &group)?;
-                            if let Some(item) = item {
-                                items.push(item);
+                            let result = parse_item(runtime, item, &group);
+                            if let Some(mut inner_items) = diagnostics.record(item, result) {
+                                items.append(&mut inner_items);
                             }
                         }
                         "group" => {
-                            let inner_items = parse_items(runtime, item, Some(&group))?;
-                            if let Some(mut inner_items) = inner_items {
+                            let result = parse_items(runtime, item, Some(&group), diagnostics);
+                            if let Some(Some(mut inner_items)) = diagnostics.record(item, result) {
                                 items.append(&mut inner_items);
                             }
                         }
+                        "exclude" => {
+                        }
                         _ => {
-                            Err(internal_error!("Invalid element: {}", item.name()))?;
+                            let error = internal_error!("Invalid element: {}", item.name());
+                            diagnostics.record::<()>(item, Err(error));
                         }
                     }
                 }
@@ -383,7 +786,7 @@ mod test {
 
     #[test]
     fn task_struct() {
-        let item = generate_task_struct();
+        let item = generate_task_struct(&mock_task("copy"));
         const EXPECTED: &str = r#"
         #[derive(Debug)]
         pub struct Task {
@@ -399,11 +802,367 @@ mod test {
         const EXPECTED: &str = r#"
         impl Task {
             pub fn run(&self) -> Result<(), Error> {
-                for item in &self.items {
-                    copy(item)?;
+                for (index, item) in self.items.iter().enumerate() {
+                    copy(item).map_err(|error| error.with_context(ErrorContext::new("copy", index, None, None)))?;
                 }
                 Ok(())
             }
+
+            pub fn describe(&self) -> Vec<String> {
+                self.items.iter().map(describe).collect()
+            }
+
+            pub fn validate(&self, produced: &mut Vec<PathBuf>) -> Vec<Error> {
+                self.items
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(index, item)| check_item(ErrorContext::new("copy", index, None, None), produced))
+                    .collect()
+            }
+        }
+        "#;
+        test_utils::compare_impl(item, EXPECTED);
+    }
+
+    fn mock_command_line(command: &str) -> CommandLineDescriptor {
+        CommandLineDescriptor {
+            variants: vec![],
+            default: Some(CommandDetails::new(command).unwrap()),
+        }
+    }
+
+    fn mock_parameter(name: &str, parameter_type: ParameterType) -> ParameterDescriptor {
+        ParameterDescriptor {
+            name: String::from(name),
+            parameter_type,
+            allow_group: GroupSetting::None,
+            defaults_to: None,
+            required: false,
+            role: None,
+        }
+    }
+
+    #[test]
+    fn command_line() {
+        let descriptor = mock_command_line("strip [-o $destination] $source");
+        let element = ElementDescriptor {
+            attributes: vec![
+                mock_parameter("source", ParameterType::Path),
+                mock_parameter("destination", ParameterType::Path),
+            ],
+            text: None,
+        };
+        let item = generate_command_line_fn(&descriptor, &element);
+        const EXPECTED: &str = r#"
+        fn command_line(item: &Item, _variant: usize) -> Command {
+            let mut call = Command::new("strip");
+            if let Some(destination) = &item.destination {
+                call.arg("-o");
+                call.arg(destination);
+            }
+            call.arg(&item.source);
+            call
+        }"#;
+        test_utils::compare_function(item, EXPECTED);
+    }
+
+    #[test]
+    fn command_line_typed_arguments() {
+        let descriptor =
+            mock_command_line("zstd [--level $level] [--mode $mode] --format $format $source");
+        let element = ElementDescriptor {
+            attributes: vec![
+                mock_parameter("source", ParameterType::Path),
+                mock_parameter("level", ParameterType::Int),
+                mock_parameter("mode", ParameterType::OctalMode),
+                mock_parameter(
+                    "format",
+                    ParameterType::Enum(vec![String::from("zstd"), String::from("gzip")]),
+                ),
+            ],
+            text: None,
+        };
+        let item = generate_command_line_fn(&descriptor, &element);
+        const EXPECTED: &str = r#"
+        fn command_line(item: &Item, _variant: usize) -> Command {
+            let mut call = Command::new("zstd");
+            if let Some(level) = &item.level {
+                call.arg("--level");
+                call.arg(level.to_string());
+            }
+            if let Some(mode) = &item.mode {
+                call.arg("--mode");
+                call.arg(mode.to_string());
+            }
+            call.arg("--format");
+            call.arg(&item.format);
+            call.arg(&item.source);
+            call
+        }"#;
+        test_utils::compare_function(item, EXPECTED);
+    }
+
+    #[test]
+    fn command_line_lists() {
+        let descriptor = mock_command_line("cc [-I $includes] [-D $defines] $sources");
+        let mut sources = mock_parameter(
+            "sources",
+            ParameterType::List(Box::new(ParameterType::Path)),
+        );
+        sources.required = true;
+        let element = ElementDescriptor {
+            attributes: vec![
+                sources,
+                mock_parameter(
+                    "includes",
+                    ParameterType::List(Box::new(ParameterType::Path)),
+                ),
+                mock_parameter("defines", ParameterType::List(Box::new(ParameterType::Int))),
+            ],
+            text: None,
+        };
+        let item = generate_command_line_fn(&descriptor, &element);
+        const EXPECTED: &str = r#"
+        fn command_line(item: &Item, _variant: usize) -> Command {
+            let mut call = Command::new("cc");
+            if let Some(includes) = &item.includes {
+                for value in includes {
+                    call.arg("-I");
+                    call.arg(value);
+                }
+            }
+            if let Some(defines) = &item.defines {
+                for value in defines {
+                    call.arg("-D");
+                    call.arg(value.to_string());
+                }
+            }
+            call.args(item.sources.iter());
+            call
+        }"#;
+        test_utils::compare_function(item, EXPECTED);
+    }
+
+    fn mock_variant(os: Option<&str>, arch: Option<&str>, command: &str) -> CommandVariant {
+        CommandVariant {
+            os: os.map(String::from),
+            arch: arch.map(String::from),
+            condition: None,
+            command: CommandDetails::new(command).unwrap(),
+        }
+    }
+
+    #[test]
+    fn command_line_variants() {
+        let mut descriptor = mock_command_line("strip $source");
+        descriptor.variants = vec![
+            mock_variant(
+                Some("linux"),
+                Some("aarch64"),
+                "aarch64-linux-gnu-strip $source",
+            ),
+            mock_variant(Some("windows"), None, "strip.exe $source"),
+        ];
+        let element = ElementDescriptor {
+            attributes: vec![mock_parameter("source", ParameterType::Path)],
+            text: None,
+        };
+        let item = generate_command_line_fn(&descriptor, &element);
+        const EXPECTED: &str = r#"
+        fn command_line(item: &Item, variant: usize) -> Command {
+            match variant {
+                0 => {
+                    let mut call = Command::new("aarch64-linux-gnu-strip");
+                    call.arg(&item.source);
+                    call
+                }
+                1 => {
+                    let mut call = Command::new("strip.exe");
+                    call.arg(&item.source);
+                    call
+                }
+                _ => {
+                    let mut call = Command::new("strip");
+                    call.arg(&item.source);
+                    call
+                }
+            }
+        }"#;
+        test_utils::compare_function(item, EXPECTED);
+    }
+
+    #[test]
+    fn select_variant() {
+        let mut strip = mock_task("strip");
+        let mut descriptor = CommandLineDescriptor {
+            variants: vec![
+                mock_variant(
+                    Some("linux"),
+                    Some("aarch64"),
+                    "aarch64-linux-gnu-strip $source",
+                ),
+                mock_variant(None, None, "strip $source"),
+            ],
+            default: None,
+        };
+        descriptor.variants[1].condition = Some(String::from("toolchain == 'gnu'"));
+        strip.command = Command::CommandLine(descriptor);
+        let item = match &strip.command {
+            Command::CommandLine(descriptor) => generate_select_variant_fn(&strip, descriptor),
+            Command::Snippet(_) => unreachable!(),
+        };
+        const EXPECTED: &str = r#"
+        fn select_variant(runtime: &Runtime) -> Result<usize, Error> {
+            select_command_variant("strip", runtime, &[(Some("linux"), Some("aarch64"), None), (None, None, Some("toolchain == 'gnu'"))], false)
+        }"#;
+        test_utils::compare_function(item, EXPECTED);
+    }
+
+    #[test]
+    fn execute_snippet() {
+        let copy = mock_task("copy");
+        let functions = generate_execute_fns(&copy);
+        assert_eq!(1, functions.len());
+        const EXPECTED: &str = r#"
+        fn copy(item: &Item) -> Result<(), Error> {
+            asdf
+        }"#;
+        test_utils::compare_function(functions[0].clone(), EXPECTED);
+    }
+
+    #[test]
+    fn execute_snippet_variants() {
+        let mut symlink = mock_task("symlink");
+        let snippet: SnippetDescriptor = serde_yaml::from_str(
+            r#"
+            variants:
+              - os: linux
+                arch: aarch64
+                code: linux_aarch64(${source})
+            windows: windows(${source})
+            unix: unix(${source})
+            "#,
+        )
+        .unwrap();
+        symlink.command = Command::Snippet(snippet);
+        let functions = generate_execute_fns(&symlink);
+        assert_eq!(4, functions.len());
+        let expected = [
+            r#"
+            #[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+            fn symlink(item: &Item) -> Result<(), Error> {
+                linux_aarch64(&item.source)
+            }"#,
+            r#"
+            #[cfg(all(target_os = "windows", not(any(all(target_os = "linux", target_arch = "aarch64")))))]
+            fn symlink(item: &Item) -> Result<(), Error> {
+                windows(&item.source)
+            }"#,
+            r#"
+            #[cfg(all(target_family = "unix", not(any(all(target_os = "linux", target_arch = "aarch64"), target_os = "windows"))))]
+            fn symlink(item: &Item) -> Result<(), Error> {
+                unix(&item.source)
+            }"#,
+            r#"
+            #[cfg(not(any(all(target_os = "linux", target_arch = "aarch64"), target_os = "windows", target_family = "unix")))]
+            fn symlink(_item: &Item) -> Result<(), Error> {
+                Err(internal_error!("Task symlink is not supported on this platform"))
+            }"#,
+        ];
+        for (function, expected) in functions.into_iter().zip(expected) {
+            test_utils::compare_function(function, expected);
+        }
+    }
+
+    #[test]
+    fn describe_command_line() {
+        let mut strip = mock_task("strip");
+        strip.command = Command::CommandLine(mock_command_line("strip $source"));
+        let item = generate_describe_fn(&strip);
+        const EXPECTED: &str = r#"
+        fn describe(item: &Item, variant: usize) -> String {
+            format!("{:?}", command_line(item, variant))
+        }"#;
+        test_utils::compare_function(item, EXPECTED);
+    }
+
+    #[test]
+    fn describe_snippet_without_description() {
+        let copy = mock_task("copy");
+        let item = generate_describe_fn(&copy);
+        const EXPECTED: &str = r#"
+        fn describe(item: &Item) -> String {
+            format!("copy {:?}", item)
+        }"#;
+        test_utils::compare_function(item, EXPECTED);
+    }
+
+    #[test]
+    fn describe_snippet_with_description() {
+        let mut copy = mock_task("copy");
+        copy.description = Some(String::from("copy {${source}} -> \"${destination}\""));
+        let item = generate_describe_fn(&copy);
+        const EXPECTED: &str = r#"
+        fn describe(item: &Item) -> String {
+            format!("copy {{{}}} -> \"{}\"", item.source.describe(), item.destination.describe())
+        }"#;
+        test_utils::compare_function(item, EXPECTED);
+    }
+
+    #[test]
+    fn describe_snippet_with_constant_description() {
+        let mut copy = mock_task("copy");
+        copy.description = Some(String::from("copy {everything}"));
+        let item = generate_describe_fn(&copy);
+        const EXPECTED: &str = r#"
+        fn describe(item: &Item) -> String {
+            String::from("copy {everything}")
+        }"#;
+        test_utils::compare_function(item, EXPECTED);
+    }
+
+    #[test]
+    fn task_impl_with_roles() {
+        let mut copy = mock_task("copy");
+        copy.element.attributes = vec![
+            ParameterDescriptor {
+                name: String::from("source"),
+                parameter_type: ParameterType::Path,
+                allow_group: GroupSetting::Prefix,
+                defaults_to: None,
+                required: true,
+                role: Some(ParameterRole::Source),
+            },
+            ParameterDescriptor {
+                name: String::from("destination"),
+                parameter_type: ParameterType::Path,
+                allow_group: GroupSetting::InheritPrefix,
+                defaults_to: None,
+                required: false,
+                role: Some(ParameterRole::Destination),
+            },
+        ];
+        let item = generate_task_impl(&copy);
+        const EXPECTED: &str = r#"
+        impl Task {
+            pub fn run(&self) -> Result<(), Error> {
+                for (index, item) in self.items.iter().enumerate() {
+                    copy(item).map_err(|error| error.with_context(ErrorContext::new("copy", index, Some(&item.source), item.destination.as_ref())))?;
+                }
+                Ok(())
+            }
+
+            pub fn describe(&self) -> Vec<String> {
+                self.items.iter().map(describe).collect()
+            }
+
+            pub fn validate(&self, produced: &mut Vec<PathBuf>) -> Vec<Error> {
+                self.items
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(index, item)| check_item(ErrorContext::new("copy", index, Some(&item.source), item.destination.as_ref()), produced))
+                    .collect()
+            }
         }
         "#;
         test_utils::compare_impl(item, EXPECTED);
@@ -411,13 +1170,13 @@ mod test {
 
     #[test]
     fn parse_task() {
-        let item = generate_parse_task();
+        let item = generate_parse_task(&mock_task("copy"));
         const EXPECTED: &str = r#"
-        pub fn parse_task(runtime: &Runtime, parent: &Element) -> Result<Option<Task>, Error> {
-            let items = parse_items(runtime, parent, None)?;
+        pub fn parse_task(runtime: &Runtime, parent: &Element, diagnostics: &mut Diagnostics) -> Result<Option<Task>, Error> {
+            let items = parse_items(runtime, parent, None, diagnostics)?;
             let task = items.map(|items| {
                 Task {
-                    items: items
+                    items
                 }
             });
             Ok(task)
@@ -425,6 +1184,56 @@ mod test {
         test_utils::compare_function(item, EXPECTED);
     }
 
+    #[test]
+    fn task_impl_command_line() {
+        let mut strip = mock_task("strip");
+        strip.command = Command::CommandLine(mock_command_line("strip $source"));
+        let item = generate_task_impl(&strip);
+        const EXPECTED: &str = r#"
+        impl Task {
+            pub fn run(&self) -> Result<(), Error> {
+                for (index, item) in self.items.iter().enumerate() {
+                    strip(item, self.variant).map_err(|error| error.with_context(ErrorContext::new("strip", index, None, None)))?;
+                }
+                Ok(())
+            }
+
+            pub fn describe(&self) -> Vec<String> {
+                self.items.iter().map(|item| describe(item, self.variant)).collect()
+            }
+
+            pub fn validate(&self, produced: &mut Vec<PathBuf>) -> Vec<Error> {
+                self.items
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(index, item)| check_item(ErrorContext::new("strip", index, None, None), produced))
+                    .collect()
+            }
+        }
+        "#;
+        test_utils::compare_impl(item, EXPECTED);
+    }
+
+    #[test]
+    fn parse_task_command_line() {
+        let mut strip = mock_task("strip");
+        strip.command = Command::CommandLine(mock_command_line("strip $source"));
+        let item = generate_parse_task(&strip);
+        const EXPECTED: &str = r#"
+        pub fn parse_task(runtime: &Runtime, parent: &Element, diagnostics: &mut Diagnostics) -> Result<Option<Task>, Error> {
+            let items = parse_items(runtime, parent, None, diagnostics)?;
+            let task = match items {
+                Some(items) => Some(Task {
+                    items,
+                    variant: select_variant(runtime)?,
+                }),
+                None => None,
+            };
+            Ok(task)
+        }"#;
+        test_utils::compare_function(item, EXPECTED);
+    }
+
     #[test]
     fn task_enum() {
         let tasks = vec![mock_task("copy"), mock_task("strip")];
@@ -434,6 +1243,8 @@ mod test {
         pub enum Task {
             Copy(copy::Task),
             Strip(strip::Task),
+            Dynamic(DynamicTask),
+            Skipped(SkippedTask),
         }"#;
         test_utils::compare_enum(enum_definition, EXPECTED);
     }
@@ -448,6 +1259,26 @@ mod test {
                 match &self {
                     Task::Copy(copy) => copy.run(),
                     Task::Strip(strip) => strip.run(),
+                    Task::Dynamic(dynamic) => dynamic.run(),
+                    Task::Skipped(skipped) => skipped.run(),
+                }
+            }
+
+            pub fn describe(&self) -> Vec<String> {
+                match &self {
+                    Task::Copy(copy) => copy.describe(),
+                    Task::Strip(strip) => strip.describe(),
+                    Task::Dynamic(dynamic) => dynamic.describe(),
+                    Task::Skipped(skipped) => skipped.describe(),
+                }
+            }
+
+            pub fn validate(&self, produced: &mut Vec<PathBuf>) -> Vec<Error> {
+                match &self {
+                    Task::Copy(copy) => copy.validate(produced),
+                    Task::Strip(strip) => strip.validate(produced),
+                    Task::Dynamic(dynamic) => dynamic.validate(produced),
+                    Task::Skipped(skipped) => skipped.validate(produced),
                 }
             }
         }"#;
@@ -459,29 +1290,27 @@ mod test {
         let tasks = vec![mock_task("copy"), mock_task("strip")];
         let parse_input_fn = generate_parse_input(&tasks);
         const EXPECTED: &str = r#"
-        fn parse_input(runtime: &Runtime, input: &str) -> Result<Vec<Task>, Error> {
-            let xml_elements: Element = input.parse()?;
-            xml_elements
-                .children()
-                .map(|task| {
-                    let task_name = task.name();
-                    match task_name {
-                        "copy" => {
-                            let task = copy::parse_task(runtime, task)?.map(|task| Task::Copy(task));
-                            Ok(task)
-                        },
-                        "strip" => {
-                            let task = strip::parse_task(runtime, task)?.map(|task| Task::Strip(task));
-                            Ok(task)
-                        },
-                        _ => Err(Error::from(format!("Invalid task '{}'", task_name))),
+        fn parse_input(runtime: &Runtime, root: &Element, diagnostics: &mut Diagnostics) -> Vec<Task> {
+            let mut tasks = Vec::new();
+            for element in root.children() {
+                let task_name = element.name();
+                let result = match task_name {
+                    "copy" => copy::parse_task(runtime, element, diagnostics).map(|task| task.map(Task::Copy)),
+                    "strip" => strip::parse_task(runtime, element, diagnostics).map(|task| task.map(Task::Strip)),
+                    _ => dynamic::parse_task(runtime, element, diagnostics).map(|task| task.map(Task::Dynamic)),
+                };
+                match diagnostics.record(element, result) {
+                    Some(Some(task)) => tasks.push(task),
+                    Some(None) => {
+                        let skipped = SkippedTask::new(element, runtime, diagnostics.location(element));
+                        if let Some(skipped) = diagnostics.record(element, skipped) {
+                            tasks.push(Task::Skipped(skipped));
+                        }
                     }
-                })
-                .filter_map(|x| match x {
-                    Ok(task) => task.map(|task| Ok(task)),
-                    Err(err) => Some(Err(err)),
-                })
-                .collect()
+                    None => {}
+                }
+            }
+            tasks
         }
         "#;
         test_utils::compare_function(parse_input_fn, EXPECTED);
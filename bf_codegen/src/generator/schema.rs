@@ -0,0 +1,453 @@
+use crate::{
+    command::{
+        ElementDescriptor, GroupSetting, ParameterDescriptor, ParameterRole, ParameterType,
+        TaskDescriptor, OCTAL_MODE_PATTERN,
+    },
+    NAMESPACE,
+};
+
+struct Writer {
+    lines: Vec<String>,
+    indent: usize,
+}
+
+impl Writer {
+    fn new() -> Writer {
+        Writer {
+            lines: Vec::new(),
+            indent: 0,
+        }
+    }
+
+    fn line(&mut self, line: &str) -> &mut Writer {
+        self.lines
+            .push(format!("{}{}", "  ".repeat(self.indent), line));
+        self
+    }
+
+    fn open(&mut self, line: &str) -> &mut Writer {
+        self.line(line);
+        self.indent += 1;
+        self
+    }
+
+    fn close(&mut self, line: &str) -> &mut Writer {
+        self.indent -= 1;
+        self.line(line)
+    }
+
+    fn attribute(&mut self, name: &str, xsd_type: &str, required: bool) -> &mut Writer {
+        let usage = if required { r#" use="required""# } else { "" };
+        self.line(&format!(
+            r#"<xs:attribute name="{}" type="{}"{}/>"#,
+            name, xsd_type, usage
+        ))
+    }
+
+    fn finish(&self) -> String {
+        self.lines.join("\n")
+    }
+}
+
+/// Escapes `value` for use inside a double quoted attribute.
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn attribute_type(task: &TaskDescriptor, parameter: &ParameterDescriptor) -> String {
+    match parameter.parameter_type {
+        ParameterType::Enum(_) => format!("bf:{}_{}", task.name, parameter.name),
+        _ => String::from(parameter.parameter_type.xsd_type()),
+    }
+}
+
+fn is_required_on_item(element: &ElementDescriptor, parameter: &ParameterDescriptor) -> bool {
+    let from_text = element.text.as_ref() == Some(&parameter.name);
+    parameter.required
+        && !from_text
+        && !parameter.parameter_type.is_list()
+        && parameter.defaults_to.is_none()
+        && matches!(parameter.allow_group, GroupSetting::None)
+}
+
+fn write_children(writer: &mut Writer, task: &TaskDescriptor) {
+    writer
+        .open(r#"<xs:choice minOccurs="0" maxOccurs="unbounded">"#)
+        .line(&format!(
+            r#"<xs:element name="item" type="bf:{}_item"/>"#,
+            task.name
+        ))
+        .line(&format!(
+            r#"<xs:element name="group" type="bf:{}_group"/>"#,
+            task.name
+        ))
+        .line(r#"<xs:element name="exclude" type="xs:string"/>"#)
+        .line(r#"<xs:element name="include" type="bf:include"/>"#);
+    for parameter in &task.element.attributes {
+        if parameter.parameter_type.is_list()
+            && !matches!(parameter.allow_group, GroupSetting::None)
+        {
+            write_list_element(writer, parameter);
+        }
+    }
+    writer.close("</xs:choice>");
+}
+
+/// List parameters can also be given as repeated child elements.
+fn write_list_element(writer: &mut Writer, parameter: &ParameterDescriptor) {
+    writer.line(&format!(
+        r#"<xs:element name="{}" type="xs:string"/>"#,
+        parameter.name
+    ));
+}
+
+fn write_group_type(writer: &mut Writer, task: &TaskDescriptor) {
+    writer.open(&format!(r#"<xs:complexType name="{}_group">"#, task.name));
+    write_children(writer, task);
+    writer
+        .attribute("condition", "xs:string", false)
+        .attribute("exclude", "xs:string", false);
+    for parameter in &task.element.attributes {
+        if !matches!(parameter.allow_group, GroupSetting::None) {
+            writer.attribute(&parameter.name, &attribute_type(task, parameter), false);
+        }
+    }
+    writer.close("</xs:complexType>");
+}
+
+fn write_task_type(writer: &mut Writer, task: &TaskDescriptor) {
+    writer
+        .open(&format!(r#"<xs:complexType name="{}_task">"#, task.name))
+        .open("<xs:complexContent>")
+        .open(&format!(r#"<xs:extension base="bf:{}_group">"#, task.name))
+        .attribute("skip_reason", "xs:string", false)
+        .close("</xs:extension>")
+        .close("</xs:complexContent>")
+        .close("</xs:complexType>");
+}
+
+fn write_item_attributes(writer: &mut Writer, task: &TaskDescriptor) {
+    let element = &task.element;
+    writer.attribute("condition", "xs:string", false);
+    if element.has_role(ParameterRole::Source) {
+        writer.attribute("required", "bf:bool", false);
+    }
+    for parameter in &element.attributes {
+        writer.attribute(
+            &parameter.name,
+            &attribute_type(task, parameter),
+            is_required_on_item(element, parameter),
+        );
+    }
+    for parameter in &element.attributes {
+        if let Some(alias) = &parameter.defaults_to {
+            let declared = element
+                .attributes
+                .iter()
+                .any(|attribute| &attribute.name == alias);
+            if !declared {
+                writer.attribute(alias, &attribute_type(task, parameter), false);
+            }
+        }
+    }
+}
+
+fn write_item_type(writer: &mut Writer, task: &TaskDescriptor) {
+    let lists: Vec<&ParameterDescriptor> = task
+        .element
+        .attributes
+        .iter()
+        .filter(|parameter| parameter.parameter_type.is_list())
+        .collect();
+    if !lists.is_empty() {
+        let mixed = if task.element.text.is_some() {
+            r#" mixed="true""#
+        } else {
+            ""
+        };
+        writer
+            .open(&format!(
+                r#"<xs:complexType name="{}_item"{}>"#,
+                task.name, mixed
+            ))
+            .open(r#"<xs:choice minOccurs="0" maxOccurs="unbounded">"#);
+        for parameter in lists {
+            write_list_element(writer, parameter);
+        }
+        writer.close("</xs:choice>");
+        write_item_attributes(writer, task);
+        writer.close("</xs:complexType>");
+        return;
+    }
+    writer.open(&format!(r#"<xs:complexType name="{}_item">"#, task.name));
+    if task.element.text.is_some() {
+        writer
+            .open("<xs:simpleContent>")
+            .open(r#"<xs:extension base="xs:string">"#);
+        write_item_attributes(writer, task);
+        writer.close("</xs:extension>").close("</xs:simpleContent>");
+    } else {
+        write_item_attributes(writer, task);
+    }
+    writer.close("</xs:complexType>");
+}
+
+fn write_enum_types(writer: &mut Writer, task: &TaskDescriptor) {
+    for parameter in &task.element.attributes {
+        if let ParameterType::Enum(values) = &parameter.parameter_type {
+            writer
+                .open(&format!(
+                    r#"<xs:simpleType name="{}_{}">"#,
+                    task.name, parameter.name
+                ))
+                .open(r#"<xs:restriction base="xs:string">"#);
+            for value in values {
+                writer.line(&format!(r#"<xs:enumeration value="{}"/>"#, escape(value)));
+            }
+            writer.close("</xs:restriction>").close("</xs:simpleType>");
+        }
+    }
+}
+
+fn write_root(writer: &mut Writer, tasks: &[&TaskDescriptor]) {
+    writer
+        .open(r#"<xs:element name="tasks">"#)
+        .open("<xs:complexType>")
+        .open("<xs:sequence>")
+        .line(r#"<xs:element name="variables" type="bf:variables" minOccurs="0"/>"#)
+        .open(r#"<xs:choice minOccurs="0" maxOccurs="unbounded">"#)
+        .line(r#"<xs:element name="include" type="bf:include"/>"#);
+    for task in tasks {
+        writer.line(&format!(
+            r#"<xs:element name="{name}" type="bf:{name}_task"/>"#,
+            name = task.name
+        ));
+    }
+    writer
+        .close("</xs:choice>")
+        .close("</xs:sequence>")
+        .attribute("version", "xs:string", false)
+        .close("</xs:complexType>")
+        .close("</xs:element>");
+}
+
+fn write_common_types(writer: &mut Writer) {
+    writer
+        .open(r#"<xs:complexType name="variables">"#)
+        .open("<xs:sequence>")
+        .open(r#"<xs:element name="variable" minOccurs="0" maxOccurs="unbounded">"#)
+        .open("<xs:complexType>")
+        .attribute("name", "xs:string", true)
+        .attribute("default", "xs:string", false)
+        .attribute("values", "xs:string", false)
+        .attribute("description", "xs:string", false)
+        .close("</xs:complexType>")
+        .close("</xs:element>")
+        .close("</xs:sequence>")
+        .attribute("strict", "bf:bool", false)
+        .close("</xs:complexType>")
+        .open(r#"<xs:complexType name="include">"#)
+        .attribute("file", "xs:string", true)
+        .close("</xs:complexType>")
+        .open(r#"<xs:simpleType name="bool">"#)
+        .open(r#"<xs:restriction base="xs:string">"#)
+        .line(r#"<xs:enumeration value="true"/>"#)
+        .line(r#"<xs:enumeration value="false"/>"#)
+        .close("</xs:restriction>")
+        .close("</xs:simpleType>")
+        .open(r#"<xs:simpleType name="octal_mode">"#)
+        .open(r#"<xs:restriction base="xs:string">"#)
+        .line(&format!(r#"<xs:pattern value="{}"/>"#, OCTAL_MODE_PATTERN))
+        .close("</xs:restriction>")
+        .close("</xs:simpleType>");
+}
+
+/// Builds an XSD describing the manifest grammar accepted by the given tasks.
+pub fn generate_schema(tasks: &[&TaskDescriptor]) -> String {
+    let mut writer = Writer::new();
+    writer
+        .line(r#"<?xml version="1.0" encoding="UTF-8"?>"#)
+        .open(&format!(
+            r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema" xmlns:bf="{ns}" targetNamespace="{ns}" elementFormDefault="qualified">"#,
+            ns = NAMESPACE
+        ));
+    write_root(&mut writer, tasks);
+    write_common_types(&mut writer);
+    for task in tasks {
+        write_task_type(&mut writer, task);
+        write_group_type(&mut writer, task);
+        write_item_type(&mut writer, task);
+        write_enum_types(&mut writer, task);
+    }
+    writer.close("</xs:schema>");
+    writer.finish()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::command::{
+        Command, ElementDescriptor, GroupSetting, ParameterDescriptor, ParameterRole,
+        ParameterType, SnippetDescriptor, TaskDescriptor,
+    };
+
+    use super::{write_enum_types, write_group_type, write_item_type, Writer};
+
+    fn new_task() -> TaskDescriptor {
+        TaskDescriptor {
+            name: String::from("copy"),
+            description: None,
+            command: Command::Snippet(SnippetDescriptor::from("")),
+            element: ElementDescriptor {
+                attributes: vec![
+                    ParameterDescriptor {
+                        name: String::from("source"),
+                        parameter_type: ParameterType::Path,
+                        allow_group: GroupSetting::Prefix,
+                        defaults_to: None,
+                        required: true,
+                        role: Some(ParameterRole::Source),
+                    },
+                    ParameterDescriptor {
+                        name: String::from("mode"),
+                        parameter_type: ParameterType::Bool,
+                        allow_group: GroupSetting::None,
+                        defaults_to: None,
+                        required: true,
+                        role: None,
+                    },
+                ],
+                text: Some(String::from("source")),
+            },
+        }
+    }
+
+    #[test]
+    fn group_type() {
+        let mut writer = Writer::new();
+        write_group_type(&mut writer, &new_task());
+        let expected = r#"<xs:complexType name="copy_group">
+  <xs:choice minOccurs="0" maxOccurs="unbounded">
+    <xs:element name="item" type="bf:copy_item"/>
+    <xs:element name="group" type="bf:copy_group"/>
+    <xs:element name="exclude" type="xs:string"/>
+    <xs:element name="include" type="bf:include"/>
+  </xs:choice>
+  <xs:attribute name="condition" type="xs:string"/>
+  <xs:attribute name="exclude" type="xs:string"/>
+  <xs:attribute name="source" type="xs:string"/>
+</xs:complexType>"#;
+        assert_eq!(expected, writer.finish());
+    }
+
+    #[test]
+    fn item_type_with_text() {
+        let mut writer = Writer::new();
+        write_item_type(&mut writer, &new_task());
+        let expected = r#"<xs:complexType name="copy_item">
+  <xs:simpleContent>
+    <xs:extension base="xs:string">
+      <xs:attribute name="condition" type="xs:string"/>
+      <xs:attribute name="required" type="bf:bool"/>
+      <xs:attribute name="source" type="xs:string"/>
+      <xs:attribute name="mode" type="bf:bool" use="required"/>
+    </xs:extension>
+  </xs:simpleContent>
+</xs:complexType>"#;
+        assert_eq!(expected, writer.finish());
+    }
+
+    #[test]
+    fn item_type_without_text() {
+        let mut task = new_task();
+        task.element.text = None;
+        task.element.attributes[0].allow_group = GroupSetting::None;
+        task.element.attributes[0].defaults_to = Some(String::from("src"));
+        let mut writer = Writer::new();
+        write_item_type(&mut writer, &task);
+        let expected = r#"<xs:complexType name="copy_item">
+  <xs:attribute name="condition" type="xs:string"/>
+  <xs:attribute name="required" type="bf:bool"/>
+  <xs:attribute name="source" type="xs:string"/>
+  <xs:attribute name="mode" type="bf:bool" use="required"/>
+  <xs:attribute name="src" type="xs:string"/>
+</xs:complexType>"#;
+        assert_eq!(expected, writer.finish());
+    }
+
+    #[test]
+    fn enum_type() {
+        let mut task = new_task();
+        task.element.attributes[1].parameter_type =
+            ParameterType::Enum(vec![String::from("fast"), String::from("best")]);
+        let mut writer = Writer::new();
+        write_item_type(&mut writer, &task);
+        write_enum_types(&mut writer, &task);
+        let expected = r#"<xs:complexType name="copy_item">
+  <xs:simpleContent>
+    <xs:extension base="xs:string">
+      <xs:attribute name="condition" type="xs:string"/>
+      <xs:attribute name="required" type="bf:bool"/>
+      <xs:attribute name="source" type="xs:string"/>
+      <xs:attribute name="mode" type="bf:copy_mode" use="required"/>
+    </xs:extension>
+  </xs:simpleContent>
+</xs:complexType>
+<xs:simpleType name="copy_mode">
+  <xs:restriction base="xs:string">
+    <xs:enumeration value="fast"/>
+    <xs:enumeration value="best"/>
+  </xs:restriction>
+</xs:simpleType>"#;
+        assert_eq!(expected, writer.finish());
+    }
+
+    #[test]
+    fn list_type() {
+        let mut task = new_task();
+        task.element.attributes[1].parameter_type =
+            ParameterType::List(Box::new(ParameterType::Path));
+        task.element.attributes[1].allow_group = GroupSetting::Append;
+        let mut writer = Writer::new();
+        write_item_type(&mut writer, &task);
+        write_group_type(&mut writer, &task);
+        let expected = r#"<xs:complexType name="copy_item" mixed="true">
+  <xs:choice minOccurs="0" maxOccurs="unbounded">
+    <xs:element name="mode" type="xs:string"/>
+  </xs:choice>
+  <xs:attribute name="condition" type="xs:string"/>
+  <xs:attribute name="required" type="bf:bool"/>
+  <xs:attribute name="source" type="xs:string"/>
+  <xs:attribute name="mode" type="xs:string"/>
+</xs:complexType>
+<xs:complexType name="copy_group">
+  <xs:choice minOccurs="0" maxOccurs="unbounded">
+    <xs:element name="item" type="bf:copy_item"/>
+    <xs:element name="group" type="bf:copy_group"/>
+    <xs:element name="exclude" type="xs:string"/>
+    <xs:element name="include" type="bf:include"/>
+    <xs:element name="mode" type="xs:string"/>
+  </xs:choice>
+  <xs:attribute name="condition" type="xs:string"/>
+  <xs:attribute name="exclude" type="xs:string"/>
+  <xs:attribute name="source" type="xs:string"/>
+  <xs:attribute name="mode" type="xs:string"/>
+</xs:complexType>"#;
+        assert_eq!(expected, writer.finish());
+    }
+
+    #[test]
+    fn enum_values_are_escaped() {
+        let mut task = new_task();
+        task.element.attributes[1].parameter_type =
+            ParameterType::Enum(vec![String::from(r#"a&"b"<c>"#)]);
+        let mut writer = Writer::new();
+        write_enum_types(&mut writer, &task);
+        assert!(writer
+            .finish()
+            .contains(r#"<xs:enumeration value="a&amp;&quot;b&quot;&lt;c&gt;"/>"#));
+    }
+}
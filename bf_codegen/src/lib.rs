@@ -1,26 +1,40 @@
-mod command;
-mod command_parser;
+pub mod command;
+pub mod command_parser;
 mod generator;
 
 use std::{
-    fs::File,
+    fs::{self, File},
     io::{Error, ErrorKind, Result, Write},
-    path::Path,
+    path::{Path, PathBuf},
 };
 
 use codegen::{Module, Scope};
 use command::TaskDescriptor;
 
-use crate::generator::{generate_parse_input, generate_task_enum, generate_task_enum_impl};
+pub use crate::generator::generate_schema;
 
-fn load_tasks(base_path: &Path) -> Result<Vec<TaskDescriptor>> {
+/// XML namespace of bf manifests.
+pub const NAMESPACE: &str = "https://github.com/glecaros/bf";
+
+use crate::generator::{
+    generate_builtin_descriptors, generate_builtin_tasks, generate_parse_input, generate_task_enum,
+    generate_task_enum_impl,
+};
+
+fn load_tasks(base_path: &Path) -> Result<Vec<(PathBuf, TaskDescriptor)>> {
     if !base_path.is_dir() {
         return Err(Error::new(
             ErrorKind::NotFound,
             "Provided path is not a directory",
         ));
     }
-    TaskDescriptor::load_from_directory(base_path)
+    TaskDescriptor::paths_in_directory(base_path)?
+        .into_iter()
+        .map(|path| {
+            let descriptor = TaskDescriptor::from_reader(File::open(&path)?)?;
+            Ok((fs::canonicalize(path)?, descriptor))
+        })
+        .collect()
 }
 
 fn validate_and_open_target_file(target_file: &Path) -> Result<File> {
@@ -44,12 +58,14 @@ fn validate_and_open_target_file(target_file: &Path) -> Result<File> {
 }
 
 pub fn generate_from_path(source_path: &Path, target_file: &Path) -> Result<()> {
-    let tasks = load_tasks(source_path)?;
+    let (paths, tasks): (Vec<PathBuf>, Vec<TaskDescriptor>) =
+        load_tasks(source_path)?.into_iter().unzip();
     let mut target_file = validate_and_open_target_file(target_file)?;
     let modules: Vec<Module> = tasks.iter().map(|command| command.generate()).collect();
     let task_enum = generate_task_enum(&tasks);
     let task_enum_impl = generate_task_enum_impl(&tasks);
     let parse_input = generate_parse_input(&tasks);
+    let schema = generate_schema(&tasks.iter().collect::<Vec<_>>());
     let mut scope = Scope::new();
     for module in modules {
         scope.push_module(module);
@@ -57,5 +73,21 @@ pub fn generate_from_path(source_path: &Path, target_file: &Path) -> Result<()>
     scope.push_enum(task_enum);
     scope.push_impl(task_enum_impl);
     scope.push_fn(parse_input);
+    scope.raw(&generate_builtin_tasks(&tasks));
+    scope.raw(&generate_builtin_descriptors(&paths));
+    scope.raw(&format!("pub const SCHEMA: &str = r##\"{}\"##;", schema));
+    writeln!(target_file, "{}", scope.to_string())
+}
+
+/// Generates only the task modules for the descriptors in `source_path`,
+/// without the `Task` enum or the schema, so that tests can exercise
+/// generated code for descriptors that are not built in.
+pub fn generate_modules_from_path(source_path: &Path, target_file: &Path) -> Result<()> {
+    let tasks = load_tasks(source_path)?;
+    let mut target_file = validate_and_open_target_file(target_file)?;
+    let mut scope = Scope::new();
+    for (_, task) in &tasks {
+        scope.push_module(task.generate().vis("pub").to_owned());
+    }
     writeln!(target_file, "{}", scope.to_string())
 }
@@ -7,11 +7,13 @@ use std::{
 fn run() -> Result<()> {
     const PATH: &str = "./tasks/";
     const TARGET_FILE: &str = "commands.rs";
+    const TEST_PATH: &str = "./src/task/testdata/";
+    const TEST_TARGET_FILE: &str = "test_commands.rs";
     println!("cargo:rerun-if-changed=tasks/");
-    let out_file = env::var_os("OUT_DIR")
-        .map(|path| PathBuf::from(&path).join(TARGET_FILE))
-        .unwrap();
-    bf_codegen::generate_from_path(Path::new(PATH), &out_file)
+    println!("cargo:rerun-if-changed=src/task/testdata/");
+    let out_dir = env::var_os("OUT_DIR").map(PathBuf::from).unwrap();
+    bf_codegen::generate_from_path(Path::new(PATH), &out_dir.join(TARGET_FILE))?;
+    bf_codegen::generate_modules_from_path(Path::new(TEST_PATH), &out_dir.join(TEST_TARGET_FILE))
 }
 
 fn main() {
@@ -0,0 +1,222 @@
+use std::{cmp::Ordering, path::Path};
+
+use eval::{Expr, Value};
+use regex::{Captures, Regex};
+
+const FUNCTION_DEFINED: &str = "defined";
+const FUNCTION_NUMBER: &str = "num";
+const LITERALS: [&str; 3] = ["true", "false", "null"];
+
+/// Rewrites comparisons between a variable and a number literal, such as
+/// `jobs > 4`, to convert the variable with `num`. Variables are strings
+/// everywhere else, so `level == '2'` keeps comparing text.
+pub fn numeric_comparisons(condition: &str) -> String {
+    const NUMBER: &str = r"-?\d+(?:\.\d+)?";
+    const OPERATOR: &str = r"\s*(?:==|!=|>=|<=|>|<)\s*";
+    let variable_first = Regex::new(&format!(
+        r"(^|[^\w.])([A-Za-z_]\w*)({}{})",
+        OPERATOR, NUMBER
+    ))
+    .unwrap();
+    let number_first = Regex::new(&format!(
+        r"(^|[^\w.])({}{})([A-Za-z_]\w*)(\s*\()?",
+        NUMBER, OPERATOR
+    ))
+    .unwrap();
+    let rewrite = |code: &str| {
+        let code = variable_first.replace_all(code, |captures: &Captures| match &captures[2] {
+            name if LITERALS.contains(&name) => captures[0].to_string(),
+            name => format!(
+                "{}{}({}){}",
+                &captures[1], FUNCTION_NUMBER, name, &captures[3]
+            ),
+        });
+        number_first
+            .replace_all(&code, |captures: &Captures| match &captures[3] {
+                name if LITERALS.contains(&name) || captures.get(4).is_some() => {
+                    captures[0].to_string()
+                }
+                name => format!(
+                    "{}{}{}({})",
+                    &captures[1], &captures[2], FUNCTION_NUMBER, name
+                ),
+            })
+            .to_string()
+    };
+    let mut rewritten = String::new();
+    let mut rest = condition;
+    while let Some(start) = rest.find(['\'', '"']) {
+        let quote = rest[start..].chars().next().unwrap();
+        let end = rest[start + 1..]
+            .find(quote)
+            .map(|end| start + end + 2)
+            .unwrap_or(rest.len());
+        rewritten.push_str(&rewrite(&rest[..start]));
+        rewritten.push_str(&rest[start..end]);
+        rest = &rest[end..];
+    }
+    rewritten.push_str(&rewrite(rest));
+    rewritten
+}
+
+/// Names of the variables a condition reads. Function names, literals and
+/// the argument of `defined(...)`, which may legitimately be undefined, are
+/// left out.
+pub fn referenced_variables(condition: &str) -> Vec<String> {
+    let chars: Vec<char> = condition.chars().collect();
+    let mut variables = Vec::new();
+    let mut index = 0;
+    while index < chars.len() {
+        let current = chars[index];
+        if current == '\'' || current == '"' {
+            index += 1;
+            while index < chars.len() && chars[index] != current {
+                index += 1;
+            }
+            index += 1;
+        } else if current.is_alphanumeric() || current == '_' {
+            let start = index;
+            while index < chars.len() && (chars[index].is_alphanumeric() || chars[index] == '_') {
+                index += 1;
+            }
+            let name: String = chars[start..index].iter().collect();
+            let after_dot = start > 0 && chars[start - 1] == '.';
+            let next = chars[index..].iter().find(|c| !c.is_whitespace());
+            if next == Some(&'(') {
+                if name == FUNCTION_DEFINED {
+                    while index < chars.len() && chars[index] != ')' {
+                        index += 1;
+                    }
+                }
+            } else if !current.is_ascii_digit() && !after_dot && !LITERALS.contains(&name.as_str())
+            {
+                variables.push(name);
+            }
+        } else {
+            index += 1;
+        }
+    }
+    variables
+}
+
+fn string_argument(function: &str, value: &Value) -> Result<String, eval::Error> {
+    match value {
+        Value::String(value) => Ok(value.clone()),
+        Value::Number(value) => Ok(value.to_string()),
+        Value::Bool(value) => Ok(value.to_string()),
+        _ => Err(eval::Error::Custom(format!(
+            "{} expects strings, got {}",
+            function, value
+        ))),
+    }
+}
+
+fn arguments(function: &str, values: &[Value], count: usize) -> Result<Vec<String>, eval::Error> {
+    if values.len() != count {
+        return Err(eval::Error::Custom(format!(
+            "{} expects {} argument(s), got {}",
+            function,
+            count,
+            values.len()
+        )));
+    }
+    values
+        .iter()
+        .map(|value| string_argument(function, value))
+        .collect()
+}
+
+/// Compares dot separated versions component by component, numerically when
+/// both components are numbers.
+pub fn compare_versions(left: &str, right: &str) -> Ordering {
+    let mut left = left.trim_start_matches('v').split('.');
+    let mut right = right.trim_start_matches('v').split('.');
+    loop {
+        let (left, right) = match (left.next(), right.next()) {
+            (None, None) => return Ordering::Equal,
+            (left, right) => (left.unwrap_or("0"), right.unwrap_or("0")),
+        };
+        let ordering = match (left.parse::<u64>(), right.parse::<u64>()) {
+            (Ok(left), Ok(right)) => left.cmp(&right),
+            _ => left.cmp(right),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+/// Registers the helper functions available in conditions.
+pub fn with_functions(expr: Expr) -> Expr {
+    expr.function(FUNCTION_DEFINED, |values| match values.as_slice() {
+        [value] => Ok(Value::Bool(!value.is_null())),
+        _ => Err(eval::Error::Custom(String::from(
+            "defined expects a single variable",
+        ))),
+    })
+    .function("exists", |values| {
+        let arguments = arguments("exists", &values, 1)?;
+        Ok(Value::Bool(Path::new(&arguments[0]).exists()))
+    })
+    .function("matches", |values| {
+        let arguments = arguments("matches", &values, 2)?;
+        let regex = Regex::new(&arguments[1]).map_err(|e| eval::Error::Custom(e.to_string()))?;
+        Ok(Value::Bool(regex.is_match(&arguments[0])))
+    })
+    .function("version_ge", |values| {
+        let arguments = arguments("version_ge", &values, 2)?;
+        let ordering = compare_versions(&arguments[0], &arguments[1]);
+        Ok(Value::Bool(ordering != Ordering::Less))
+    })
+    .function(FUNCTION_NUMBER, |values| {
+        let arguments = arguments(FUNCTION_NUMBER, &values, 1)?;
+        let value = arguments[0].trim();
+        value
+            .parse::<i64>()
+            .map(Value::from)
+            .or_else(|_| value.parse::<f64>().map(Value::from))
+            .map_err(|_| {
+                eval::Error::Custom(format!("num expects a number, got '{}'", arguments[0]))
+            })
+    })
+    .function("in", |values| match values.split_first() {
+        Some((value, candidates)) => Ok(Value::Bool(candidates.contains(value))),
+        None => Err(eval::Error::Custom(String::from(
+            "in expects a value and the candidates to compare it with",
+        ))),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::cmp::Ordering;
+
+    use super::{compare_versions, numeric_comparisons, referenced_variables};
+
+    #[test]
+    fn referenced_variables_skip_strings_and_functions() {
+        let variables = referenced_variables(
+            "os == 'linux' && in(arch, 'x86_64', \"aarch64\") && !defined(debug) && count > 10",
+        );
+        assert_eq!(vec!["os", "arch", "count"], variables);
+    }
+
+    #[test]
+    fn numeric_comparisons_only_against_numbers() {
+        assert_eq!("num(jobs) > 4", numeric_comparisons("jobs > 4"));
+        assert_eq!("4 <= num(jobs)", numeric_comparisons("4 <= jobs"));
+        assert_eq!("level == '2'", numeric_comparisons("level == '2'"));
+        assert_eq!(
+            "os == 'a == 1' && num(x)>=1.5",
+            numeric_comparisons("os == 'a == 1' && x>=1.5")
+        );
+        assert_eq!("flag == true", numeric_comparisons("flag == true"));
+    }
+
+    #[test]
+    fn versions() {
+        assert_eq!(Ordering::Greater, compare_versions("1.10", "1.9"));
+        assert_eq!(Ordering::Equal, compare_versions("2.0", "2"));
+        assert_eq!(Ordering::Less, compare_versions("v1.2.3", "1.3"));
+    }
+}
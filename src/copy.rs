@@ -0,0 +1,320 @@
+use std::{
+    fs::{self, File},
+    io,
+    path::{Component, Path, PathBuf},
+};
+
+use filetime::FileTime;
+use log::debug;
+
+use crate::{
+    error::Error,
+    internal_error,
+    util::{resolve_destination, FileMode},
+};
+
+#[derive(Debug, Clone, Copy)]
+pub struct CopyOptions {
+    pub preserve_symlinks: bool,
+    pub preserve_permissions: bool,
+    pub preserve_times: bool,
+}
+
+impl CopyOptions {
+    pub fn new(
+        preserve_symlinks: &Option<bool>,
+        preserve_permissions: &Option<bool>,
+        preserve_times: &Option<bool>,
+    ) -> CopyOptions {
+        CopyOptions {
+            preserve_symlinks: preserve_symlinks.unwrap_or(false),
+            preserve_permissions: preserve_permissions.unwrap_or(true),
+            preserve_times: preserve_times.unwrap_or(false),
+        }
+    }
+}
+
+/// Resolves the destination of `source` and creates the directory holding it.
+pub fn prepare_destination(source: &Path, destination: &Path) -> Result<PathBuf, Error> {
+    let destination = resolve_destination(source, destination);
+    if let Some(directory) = destination.parent() {
+        fs::create_dir_all(directory)?;
+    }
+    Ok(destination)
+}
+
+pub fn copy(source: &Path, destination: &Path, options: &CopyOptions) -> Result<(), Error> {
+    let destination = prepare_destination(source, destination)?;
+    copy_entry(source, &destination, options, &mut Vec::new())
+}
+
+/// `ancestors` holds the canonical paths of the directories being copied, so
+/// that a symbolic link back into one of them is reported instead of followed
+/// forever.
+fn copy_entry(
+    source: &Path,
+    destination: &Path,
+    options: &CopyOptions,
+    ancestors: &mut Vec<PathBuf>,
+) -> Result<(), Error> {
+    let metadata = fs::symlink_metadata(source)?;
+    if metadata.file_type().is_symlink() && options.preserve_symlinks {
+        copy_symlink(source, destination, options)
+    } else if source.is_dir() {
+        let canonical = fs::canonicalize(source)?;
+        if ancestors.contains(&canonical) {
+            return Err(internal_error!(
+                "Symbolic link loop at {}",
+                source.to_string_lossy()
+            ));
+        }
+        ancestors.push(canonical);
+        let result = copy_directory(source, destination, options, ancestors);
+        ancestors.pop();
+        result
+    } else {
+        copy_file(source, destination, options)
+    }
+}
+
+fn copy_directory(
+    source: &Path,
+    destination: &Path,
+    options: &CopyOptions,
+    ancestors: &mut Vec<PathBuf>,
+) -> Result<(), Error> {
+    debug!(
+        "Copying directory {} to {}",
+        source.to_string_lossy(),
+        destination.to_string_lossy()
+    );
+    fs::create_dir_all(destination)?;
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        copy_entry(
+            &entry.path(),
+            &destination.join(entry.file_name()),
+            options,
+            ancestors,
+        )?;
+    }
+    let metadata = fs::metadata(source)?;
+    if options.preserve_times {
+        filetime::set_file_times(
+            destination,
+            FileTime::from_last_access_time(&metadata),
+            FileTime::from_last_modification_time(&metadata),
+        )?;
+    }
+    if options.preserve_permissions {
+        fs::set_permissions(destination, metadata.permissions())?;
+    }
+    Ok(())
+}
+
+fn copy_file(source: &Path, destination: &Path, options: &CopyOptions) -> Result<(), Error> {
+    debug!(
+        "Copying file {} to {}",
+        source.to_string_lossy(),
+        destination.to_string_lossy()
+    );
+    let metadata = fs::metadata(source)?;
+    if options.preserve_permissions {
+        fs::copy(source, destination)?;
+    } else {
+        let mut reader = File::open(source)?;
+        let mut writer = File::create(destination)?;
+        io::copy(&mut reader, &mut writer)?;
+    }
+    if options.preserve_times {
+        filetime::set_file_times(
+            destination,
+            FileTime::from_last_access_time(&metadata),
+            FileTime::from_last_modification_time(&metadata),
+        )?;
+    }
+    Ok(())
+}
+
+fn copy_symlink(source: &Path, destination: &Path, options: &CopyOptions) -> Result<(), Error> {
+    let target = fs::read_link(source)?;
+    debug!(
+        "Creating symlink {} -> {}",
+        destination.to_string_lossy(),
+        target.to_string_lossy()
+    );
+    if fs::symlink_metadata(destination).is_ok() {
+        fs::remove_file(destination)?;
+    }
+    create_symlink(source, &target, destination)?;
+    if options.preserve_times {
+        let metadata = fs::symlink_metadata(source)?;
+        filetime::set_symlink_file_times(
+            destination,
+            FileTime::from_last_access_time(&metadata),
+            FileTime::from_last_modification_time(&metadata),
+        )?;
+    }
+    Ok(())
+}
+
+/// Where to create a link to `source` at `destination`, and the target it
+/// should hold. The target is relative to the link's directory, so the two can
+/// be moved together, and names `source` itself rather than whatever it links
+/// to. A link left there by an earlier run is replaced.
+pub fn prepare_symlink(source: &Path, destination: &Path) -> Result<(PathBuf, PathBuf), Error> {
+    let link = prepare_destination(source, destination)?;
+    let name = source
+        .file_name()
+        .ok_or_else(|| internal_error!("Invalid link source {}", source.to_string_lossy()))?;
+    let target = canonical_directory(source)?.join(name);
+    let target = relative_path(&target, &canonical_directory(&link)?);
+    if matches!(fs::symlink_metadata(&link), Ok(metadata) if metadata.file_type().is_symlink()) {
+        fs::remove_file(&link).or_else(|_| fs::remove_dir(&link))?;
+    }
+    debug!(
+        "Creating symlink {} -> {}",
+        link.to_string_lossy(),
+        target.to_string_lossy()
+    );
+    Ok((link, target))
+}
+
+fn canonical_directory(path: &Path) -> io::Result<PathBuf> {
+    match path.parent() {
+        Some(directory) if !directory.as_os_str().is_empty() => fs::canonicalize(directory),
+        _ => fs::canonicalize("."),
+    }
+}
+
+/// `path` relative to `base`, both absolute.
+fn relative_path(path: &Path, base: &Path) -> PathBuf {
+    let common = path
+        .components()
+        .zip(base.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    base.components()
+        .skip(common)
+        .map(|_| Component::ParentDir)
+        .chain(path.components().skip(common))
+        .collect()
+}
+
+#[cfg(unix)]
+pub fn set_mode(path: &Path, mode: &FileMode) -> Result<(), Error> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode.0))?;
+    Ok(())
+}
+
+/// Windows only has a read-only flag, set when the mode grants no write access.
+#[cfg(windows)]
+pub fn set_mode(path: &Path, mode: &FileMode) -> Result<(), Error> {
+    let mut permissions = fs::metadata(path)?.permissions();
+    permissions.set_readonly(mode.0 & 0o222 == 0);
+    fs::set_permissions(path, permissions)?;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn create_symlink(_source: &Path, target: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn create_symlink(source: &Path, target: &Path, link: &Path) -> io::Result<()> {
+    if source.is_dir() {
+        std::os::windows::fs::symlink_dir(target, link)
+    } else {
+        std::os::windows::fs::symlink_file(target, link)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, path::PathBuf};
+
+    use filetime::FileTime;
+
+    use crate::test_utils::fixture;
+
+    use super::{copy, CopyOptions};
+
+    fn copy_fixture(name: &str) -> PathBuf {
+        let files =
+            ["src/a.txt", "src/nested/b.txt", "src/nested/deeper/c.txt"].map(|file| (file, file));
+        fixture(&format!("copy_{}", name), &files)
+    }
+
+    #[test]
+    fn copy_directory_tree() {
+        let root = copy_fixture("tree");
+        let options = CopyOptions::new(&None, &None, &None);
+        copy(&root.join("src"), &root.join("out"), &options).unwrap();
+        for file in ["a.txt", "nested/b.txt", "nested/deeper/c.txt"] {
+            let contents = fs::read_to_string(root.join("out").join(file)).unwrap();
+            assert_eq!(format!("src/{}", file), contents);
+        }
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn copy_directory_into_directory() {
+        let root = copy_fixture("into");
+        let options = CopyOptions::new(&None, &None, &None);
+        let mut destination = root.join("out").to_string_lossy().to_string();
+        destination.push(std::path::MAIN_SEPARATOR);
+        copy(
+            &root.join("src/nested"),
+            &PathBuf::from(destination),
+            &options,
+        )
+        .unwrap();
+        assert!(root.join("out/nested/deeper/c.txt").is_file());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn copy_preserves_times() {
+        let root = copy_fixture("times");
+        let source = root.join("src/a.txt");
+        let mtime = FileTime::from_unix_time(1_000_000_000, 0);
+        filetime::set_file_mtime(&source, mtime).unwrap();
+        let options = CopyOptions::new(&None, &None, &Some(true));
+        copy(&source, &root.join("out/a.txt"), &options).unwrap();
+        let metadata = fs::metadata(root.join("out/a.txt")).unwrap();
+        assert_eq!(mtime, FileTime::from_last_modification_time(&metadata));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn copy_symlinks() {
+        let root = copy_fixture("symlinks");
+        std::os::unix::fs::symlink("a.txt", root.join("src/link.txt")).unwrap();
+        let preserve = CopyOptions::new(&Some(true), &None, &None);
+        copy(&root.join("src"), &root.join("preserved"), &preserve).unwrap();
+        let link = fs::symlink_metadata(root.join("preserved/link.txt")).unwrap();
+        assert!(link.file_type().is_symlink());
+        let follow = CopyOptions::new(&Some(false), &None, &None);
+        copy(&root.join("src"), &root.join("followed"), &follow).unwrap();
+        let file = fs::symlink_metadata(root.join("followed/link.txt")).unwrap();
+        assert!(file.file_type().is_file());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn copy_symlink_loop() {
+        let root = copy_fixture("loop");
+        std::os::unix::fs::symlink("..", root.join("src/nested/parent")).unwrap();
+        let follow = CopyOptions::new(&Some(false), &None, &None);
+        assert!(copy(&root.join("src"), &root.join("followed"), &follow).is_err());
+        let preserve = CopyOptions::new(&Some(true), &None, &None);
+        copy(&root.join("src"), &root.join("preserved"), &preserve).unwrap();
+        let link = fs::symlink_metadata(root.join("preserved/nested/parent")).unwrap();
+        assert!(link.file_type().is_symlink());
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -1,12 +1,205 @@
+use std::{path::PathBuf, rc::Rc};
+
+use minidom::Element;
+
+use crate::include::Manifest;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    Parse,
+    Validation,
+    Execution,
+}
+
+impl ErrorKind {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ErrorKind::Parse => 3,
+            ErrorKind::Validation => 4,
+            ErrorKind::Execution => 5,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ErrorContext {
+    pub task: String,
+    pub item: usize,
+    pub source: Option<PathBuf>,
+    pub destination: Option<PathBuf>,
+}
+
+impl ErrorContext {
+    pub fn new(
+        task: &str,
+        item: usize,
+        source: Option<&PathBuf>,
+        destination: Option<&PathBuf>,
+    ) -> ErrorContext {
+        ErrorContext {
+            task: String::from(task),
+            item,
+            source: source.cloned(),
+            destination: destination.cloned(),
+        }
+    }
+}
+
+/// Position of an element in the manifest it was read from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub file: PathBuf,
+    /// Line and column of the element, only known for XML manifests.
+    pub position: Option<(usize, usize)>,
+}
+
+impl std::fmt::Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.position {
+            Some((line, column)) => {
+                write!(f, "{}:{}:{}", self.file.to_string_lossy(), line, column)
+            }
+            None => write!(f, "{}", self.file.to_string_lossy()),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Error {
     pub message: String,
+    pub kind: Option<ErrorKind>,
+    pub context: Option<Box<ErrorContext>>,
+    pub location: Option<Box<Location>>,
+    pub diagnostics: Vec<Error>,
+}
+
+impl Error {
+    pub fn with_kind(mut self, kind: ErrorKind) -> Self {
+        self.kind = Some(kind);
+        self
+    }
+
+    pub fn with_context(mut self, context: ErrorContext) -> Self {
+        self.context = Some(Box::new(context));
+        self
+    }
+
+    pub fn with_location(mut self, location: Location) -> Self {
+        self.location = Some(Box::new(location));
+        self
+    }
+
+    /// Locates the error at `location`, unless it already carries a more
+    /// precise one.
+    pub fn at(self, location: Option<Location>) -> Self {
+        match (&self.location, location) {
+            (None, Some(location)) => self.with_location(location),
+            _ => self,
+        }
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        self.kind.map(|kind| kind.exit_code()).unwrap_or(1)
+    }
+
+    fn describe(&self) -> String {
+        let mut description = match &self.location {
+            Some(location) => format!("{}: {}", location, self.message),
+            None => self.message.clone(),
+        };
+        if let Some(context) = &self.context {
+            description.push_str(&format!(
+                "\n  task:        {}\n  item:        {}",
+                context.task, context.item
+            ));
+            if let Some(source) = &context.source {
+                description.push_str(&format!("\n  source:      {}", source.to_string_lossy()));
+            }
+            if let Some(destination) = &context.destination {
+                description.push_str(&format!(
+                    "\n  destination: {}",
+                    destination.to_string_lossy()
+                ));
+            }
+        }
+        description
+    }
+
+    pub fn report(&self) -> String {
+        let kind = match self.kind {
+            Some(ErrorKind::Parse) => "Manifest parse error",
+            Some(ErrorKind::Validation) => "Manifest validation error",
+            Some(ErrorKind::Execution) => "Task execution error",
+            None => "Error",
+        };
+        let mut report = format!("{}: {}", kind, self.describe());
+        for diagnostic in &self.diagnostics {
+            report.push_str("\n  ");
+            report.push_str(&diagnostic.describe().replace('\n', "\n  "));
+        }
+        report
+    }
+}
+
+/// Collects the errors found while parsing a manifest so that parsing can go
+/// on and every problem is reported at once.
+#[derive(Debug, Default)]
+pub struct Diagnostics {
+    errors: Vec<Error>,
+    manifest: Option<Rc<Manifest>>,
+}
+
+impl Diagnostics {
+    /// Locates the errors recorded from now on within `manifest`.
+    pub fn locate_in(&mut self, manifest: Rc<Manifest>) {
+        self.manifest = Some(manifest);
+    }
+
+    /// Where `element`, taken from the manifest errors are located in, was
+    /// read from.
+    pub fn location(&self, element: &Element) -> Option<Location> {
+        self.manifest
+            .as_ref()
+            .and_then(|manifest| manifest.location(element))
+    }
+
+    /// Keeps the error of a failed `result`, located at `element`.
+    pub fn record<T>(&mut self, element: &Element, result: Result<T, Error>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(error) => {
+                let error = error.at(self.location(element));
+                self.errors.push(error);
+                None
+            }
+        }
+    }
+
+    pub fn push(&mut self, error: Error) {
+        self.errors.push(error);
+    }
+
+    pub fn finish<T>(mut self, value: T) -> Result<T, Error> {
+        match self.errors.len() {
+            0 => Ok(value),
+            1 => Err(self.errors.remove(0)),
+            count => {
+                let mut error = Error::from(format!("Found {} problems in the manifest", count));
+                error.diagnostics = self.errors;
+                Err(error)
+            }
+        }
+    }
 }
 
 impl<T: std::fmt::Display> From<T> for Error {
     fn from(err: T) -> Self {
         Error {
             message: format!("{}", err),
+            kind: None,
+            context: None,
+            location: None,
+            diagnostics: Vec::new(),
         }
     }
 }
@@ -15,7 +208,70 @@ impl<T: std::fmt::Display> From<T> for Error {
 macro_rules! internal_error {
     ($($arg:tt)*) => {{
         let message = format!($($arg)+);
-        let error = crate::error::Error::from(message);
+        let error = $crate::error::Error::from(message);
         error
     }}
 }
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::{Error, ErrorContext, ErrorKind, Location};
+
+    #[test]
+    fn exit_code_without_kind() {
+        let error = Error::from("failure");
+        assert_eq!(1, error.exit_code());
+    }
+
+    #[test]
+    fn exit_code_by_kind() {
+        assert_eq!(3, Error::from("").with_kind(ErrorKind::Parse).exit_code());
+        assert_eq!(
+            4,
+            Error::from("").with_kind(ErrorKind::Validation).exit_code()
+        );
+        assert_eq!(
+            5,
+            Error::from("").with_kind(ErrorKind::Execution).exit_code()
+        );
+    }
+
+    #[test]
+    fn report_with_context() {
+        let source = PathBuf::from("srcdir/lib1.so");
+        let error = Error::from("No such file or directory")
+            .with_kind(ErrorKind::Execution)
+            .with_context(ErrorContext::new("copy", 2, Some(&source), None));
+        const EXPECTED: &str = "Task execution error: No such file or directory
+  task:        copy
+  item:        2
+  source:      srcdir/lib1.so";
+        assert_eq!(EXPECTED, error.report());
+    }
+
+    #[test]
+    fn report_with_diagnostics() {
+        let location = |line| Location {
+            file: PathBuf::from("bf.xml"),
+            position: Some((line, 5)),
+        };
+        let included = Location {
+            file: PathBuf::from("common.yaml"),
+            position: None,
+        };
+        let mut error =
+            Error::from("Found 3 problems in the manifest").with_kind(ErrorKind::Validation);
+        error.diagnostics = vec![
+            Error::from("Invalid element: itme").with_location(location(3)),
+            Error::from("Variable arch was not provided").with_location(location(7)),
+            Error::from("Invalid element: grop").with_location(included),
+        ];
+        const EXPECTED: &str = "Manifest validation error: Found 3 problems in the manifest
+  bf.xml:3:5: Invalid element: itme
+  bf.xml:7:5: Variable arch was not provided
+  common.yaml: Invalid element: grop";
+        assert_eq!(EXPECTED, error.report());
+    }
+}
@@ -0,0 +1,179 @@
+use std::path::Path;
+
+use minidom::Element;
+use serde_json::Value;
+
+pub use bf_codegen::NAMESPACE;
+
+use crate::{error::Error, internal_error};
+
+pub const ELEMENT_TASKS: &str = "tasks";
+const KEY_CHILDREN: &str = "children";
+const KEY_TEXT: &str = "text";
+
+/// Serialization used by a manifest. Structured formats describe the same
+/// element tree as XML: every element is a single-key mapping from its name to
+/// either its text or a mapping of attributes, where `children` lists the
+/// nested elements in order and `text` holds the text content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    Xml,
+    Yaml,
+    Toml,
+    Json,
+}
+
+impl Format {
+    /// Picks the format from the file extension, falling back to XML.
+    pub fn from_path(path: &Path) -> Format {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("yaml") | Some("yml") => Format::Yaml,
+            Some("toml") => Format::Toml,
+            Some("json") => Format::Json,
+            _ => Format::Xml,
+        }
+    }
+
+    pub fn parse(&self, contents: &str) -> Result<Element, Error> {
+        let value: Value = match self {
+            Format::Xml => return Ok(contents.parse()?),
+            Format::Yaml => serde_yaml::from_str(contents)?,
+            Format::Toml => toml::from_str(contents)?,
+            Format::Json => serde_json::from_str(contents)?,
+        };
+        element_from_value(ELEMENT_TASKS, &value)
+    }
+}
+
+fn scalar_to_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(value) => Some(value.clone()),
+        Value::Bool(value) => Some(value.to_string()),
+        Value::Number(value) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+fn element_from_value(name: &str, value: &Value) -> Result<Element, Error> {
+    let mut element = Element::bare(name, NAMESPACE);
+    match value {
+        Value::Null => {}
+        Value::Object(entries) => {
+            for (key, value) in entries {
+                match (key.as_str(), value) {
+                    (KEY_CHILDREN, Value::Array(children)) => {
+                        for child in children {
+                            element.append_child(child_from_value(child)?);
+                        }
+                    }
+                    (KEY_CHILDREN, _) => {
+                        return Err(internal_error!("{}.children must be a list", name));
+                    }
+                    (key, value) => {
+                        let value = scalar_to_string(value).ok_or_else(|| {
+                            internal_error!("{}.{} must be a string, number or boolean", name, key)
+                        })?;
+                        if key == KEY_TEXT {
+                            element.append_text_node(value);
+                        } else {
+                            element.set_attr(key, value);
+                        }
+                    }
+                }
+            }
+        }
+        value => {
+            let text = scalar_to_string(value).ok_or_else(|| {
+                internal_error!("{} must be a mapping, string, number or boolean", name)
+            })?;
+            element.append_text_node(text);
+        }
+    }
+    Ok(element)
+}
+
+fn child_from_value(value: &Value) -> Result<Element, Error> {
+    match value {
+        Value::Object(entries) if entries.len() == 1 => {
+            let (name, value) = entries.iter().next().unwrap();
+            element_from_value(name, value)
+        }
+        _ => Err(internal_error!(
+            "Children must be mappings with a single key naming the element"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use super::Format;
+
+    fn items(element: &minidom::Element) -> Vec<String> {
+        element
+            .children()
+            .flat_map(|task| task.children())
+            .map(|item| format!("{:?}:{}", item.attr("destination"), item.text()))
+            .collect()
+    }
+
+    #[test]
+    fn format_from_extension() {
+        assert_eq!(Format::Yaml, Format::from_path(Path::new("bf.yml")));
+        assert_eq!(Format::Toml, Format::from_path(Path::new("bf.toml")));
+        assert_eq!(Format::Json, Format::from_path(Path::new("bf.json")));
+        assert_eq!(Format::Xml, Format::from_path(Path::new("bf.manifest")));
+    }
+
+    #[test]
+    fn structured_formats_match_xml() {
+        let xml = Format::Xml
+            .parse(
+                r#"<tasks xmlns="https://github.com/glecaros/bf">
+                    <copy><item>lib1.so</item><item destination="README.md">my_doc.md</item></copy>
+                </tasks>"#,
+            )
+            .unwrap();
+        let yaml = Format::Yaml
+            .parse(
+                r#"
+children:
+  - copy:
+      children:
+        - item: lib1.so
+        - item: { destination: README.md, text: my_doc.md }
+"#,
+            )
+            .unwrap();
+        let toml = Format::Toml
+            .parse(
+                r#"
+[[children]]
+[[children.copy.children]]
+item = "lib1.so"
+[[children.copy.children]]
+item = { destination = "README.md", text = "my_doc.md" }
+"#,
+            )
+            .unwrap();
+        let json = Format::Json
+            .parse(
+                r#"{"children": [{"copy": {"children": [
+                    {"item": "lib1.so"},
+                    {"item": {"destination": "README.md", "text": "my_doc.md"}}
+                ]}}]}"#,
+            )
+            .unwrap();
+        let expected = items(&xml);
+        assert_eq!(expected, items(&yaml));
+        assert_eq!(expected, items(&toml));
+        assert_eq!(expected, items(&json));
+    }
+
+    #[test]
+    fn invalid_child() {
+        let result = Format::Yaml.parse("children: [copy, item]");
+        assert!(matches!(result, Err(_)));
+    }
+}
@@ -0,0 +1,344 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use log::debug;
+use minidom::{Element, Node};
+
+use crate::{
+    error::{Error, ErrorKind, Location},
+    format::{Format, ELEMENT_TASKS, NAMESPACE},
+    internal_error,
+    position::{syntax_error_location, Positions},
+    runtime::Runtime,
+    util::interpolate_attribute,
+    variables::ELEMENT_VARIABLES,
+};
+
+pub const ELEMENT_INCLUDE: &str = "include";
+const ATTR_FILE: &str = "file";
+const ATTR_VERSION: &str = "version";
+const SUPPORTED_VERSIONS: [&str; 1] = ["1"];
+
+/// A parsed manifest, along with where each of its elements was read from.
+#[derive(Debug)]
+pub struct Manifest {
+    pub root: Element,
+    pub positions: Positions,
+}
+
+impl Manifest {
+    /// Where `element`, the root of the manifest or one of its descendants,
+    /// was read from.
+    pub fn location(&self, element: &Element) -> Option<Location> {
+        self.positions.find(&self.root, element)
+    }
+
+    /// Takes out the first child element with the given name, keeping its
+    /// positions with it.
+    pub fn remove_child(&mut self, name: &str, namespace: &str) -> Option<Manifest> {
+        let index = self
+            .root
+            .children()
+            .position(|child| child.is(name, namespace))?;
+        let root = self.root.remove_child(name, namespace)?;
+        let children = &mut self.positions.children;
+        let positions = match index < children.len() {
+            true => children.remove(index),
+            false => Positions::default(),
+        };
+        Some(Manifest { root, positions })
+    }
+}
+
+pub fn read_manifest(path: &Path, format: Option<Format>) -> Result<Manifest, Error> {
+    let contents = fs::read_to_string(path).map_err(|e| {
+        internal_error!("Could not read {}: {}", path.to_string_lossy(), e)
+            .with_kind(ErrorKind::Parse)
+    })?;
+    let format = format.unwrap_or_else(|| Format::from_path(path));
+    let root = format.parse(&contents).map_err(|e| {
+        let error = internal_error!("Could not parse {}: {}", path.to_string_lossy(), e.message)
+            .with_kind(ErrorKind::Parse);
+        match syntax_error_location(&contents, path) {
+            Some(location) if format == Format::Xml => error.with_location(location),
+            _ => error,
+        }
+    })?;
+    let positions = match format {
+        Format::Xml => Positions::parse(&contents, path, &root),
+        _ => Positions::file(path, &root),
+    };
+    validate_root(&root).map_err(|e| {
+        internal_error!(
+            "{} is not a bf manifest: {}",
+            path.to_string_lossy(),
+            e.message
+        )
+        .with_kind(ErrorKind::Validation)
+    })?;
+    Ok(Manifest { root, positions })
+}
+
+/// Checks that the document is a `<tasks>` element in the bf namespace, using
+/// a schema version this build understands. A missing version means the
+/// current one.
+fn validate_root(root: &Element) -> Result<(), Error> {
+    if !root.is(ELEMENT_TASKS, NAMESPACE) {
+        return Err(internal_error!(
+            "expected root element <{} xmlns=\"{}\">, found <{} xmlns=\"{}\">",
+            ELEMENT_TASKS,
+            NAMESPACE,
+            root.name(),
+            root.ns()
+        ));
+    }
+    match root.attr(ATTR_VERSION) {
+        Some(version) if !SUPPORTED_VERSIONS.contains(&version) => Err(internal_error!(
+            "unsupported schema version {}, expected one of: {}",
+            version,
+            SUPPORTED_VERSIONS.join(", ")
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Replaces every `<include>` element under `root` with the children of the
+/// root element of the manifest it names. Included paths are resolved relative
+/// to the directory of the manifest that includes them.
+pub fn expand_includes(
+    manifest: &Manifest,
+    path: &Path,
+    runtime: &Runtime,
+) -> Result<Manifest, Error> {
+    let mut stack = vec![fs::canonicalize(path)?];
+    expand_element(&manifest.root, &manifest.positions, &mut stack, runtime)
+}
+
+fn expand_element(
+    element: &Element,
+    positions: &Positions,
+    stack: &mut Vec<PathBuf>,
+    runtime: &Runtime,
+) -> Result<Manifest, Error> {
+    let root = element
+        .attrs()
+        .fold(
+            Element::builder(element.name(), element.ns()),
+            |builder, (name, value)| builder.attr(name, value),
+        )
+        .build();
+    let mut expanded = Manifest {
+        root,
+        positions: Positions {
+            location: positions.location.clone(),
+            children: Vec::new(),
+        },
+    };
+    let unknown = Positions::default();
+    let mut child_positions = positions.children.iter();
+    for node in element.nodes() {
+        match node {
+            Node::Element(child) => {
+                let positions = child_positions.next().unwrap_or(&unknown);
+                if child.is(ELEMENT_INCLUDE, NAMESPACE) {
+                    let mut included = include(child, stack, runtime)
+                        .map_err(|e| e.at(positions.location.clone()))?;
+                    for node in included.root.nodes_mut() {
+                        let node = std::mem::replace(node, Node::Text(String::new()));
+                        expanded.root.append_node(node);
+                    }
+                    expanded
+                        .positions
+                        .children
+                        .append(&mut included.positions.children);
+                } else {
+                    let child = expand_element(child, positions, stack, runtime)?;
+                    expanded.root.append_child(child.root);
+                    expanded.positions.children.push(child.positions);
+                }
+            }
+            Node::Text(text) => expanded.root.append_text_node(text.clone()),
+        }
+    }
+    Ok(expanded)
+}
+
+fn include(
+    element: &Element,
+    stack: &mut Vec<PathBuf>,
+    runtime: &Runtime,
+) -> Result<Manifest, Error> {
+    let file = interpolate_attribute(ATTR_FILE, element, runtime)?
+        .ok_or_else(|| internal_error!("Include without a file attribute"))?;
+    let including = stack.last().and_then(|path| path.parent());
+    let path = including.unwrap_or(Path::new("")).join(file);
+    let path = fs::canonicalize(&path).map_err(|e| {
+        internal_error!(
+            "Could not resolve include {}: {}",
+            path.to_string_lossy(),
+            e
+        )
+    })?;
+    if stack.contains(&path) {
+        let chain: Vec<String> = stack
+            .iter()
+            .chain(std::iter::once(&path))
+            .map(|path| path.to_string_lossy().to_string())
+            .collect();
+        return Err(internal_error!(
+            "Include cycle detected: {}",
+            chain.join(" -> ")
+        ));
+    }
+    debug!("Including {}", path.to_string_lossy());
+    let manifest = read_manifest(&path, None)?;
+    if manifest
+        .root
+        .children()
+        .any(|child| child.name() == ELEMENT_VARIABLES)
+    {
+        return Err(internal_error!(
+            "Variables can only be declared in the top level manifest, found in {}",
+            path.to_string_lossy()
+        ));
+    }
+    stack.push(path);
+    let expanded = expand_element(&manifest.root, &manifest.positions, stack, runtime)?;
+    stack.pop();
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, path::PathBuf};
+
+    use crate::{error::Location, runtime::Runtime, test_utils::fixture};
+
+    use super::{expand_includes, read_manifest, validate_root, ELEMENT_INCLUDE};
+
+    #[test]
+    fn include_is_expanded() {
+        let root = fixture(
+            "include_expanded",
+            &[
+                (
+                    "main.xml",
+                    r#"<tasks xmlns="https://github.com/glecaros/bf">
+                        <include file="common/{component}.xml"/>
+                        <copy><item>main.txt</item></copy>
+                    </tasks>"#,
+                ),
+                (
+                    "common/lib.xml",
+                    r#"<tasks xmlns="https://github.com/glecaros/bf">
+                        <copy><item>lib.txt</item></copy>
+                        <include file="extra.yaml"/>
+                    </tasks>"#,
+                ),
+                (
+                    "common/extra.yaml",
+                    "children:\n  - copy:\n      children:\n        - item: extra.txt\n",
+                ),
+            ],
+        );
+        let mut runtime = Runtime::default();
+        runtime
+            .variables
+            .push((String::from("component"), String::from("lib")));
+        let path = root.join("main.xml");
+        let manifest = read_manifest(&path, None).unwrap();
+        let expanded = expand_includes(&manifest, &path, &runtime).unwrap();
+        let items: Vec<String> = expanded
+            .root
+            .children()
+            .map(|task| task.children().next().unwrap().text())
+            .collect();
+        assert_eq!(vec!["lib.txt", "extra.txt", "main.txt"], items);
+        let locations: Vec<Location> = expanded
+            .root
+            .children()
+            .filter_map(|task| expanded.location(task))
+            .collect();
+        let files: Vec<PathBuf> = locations
+            .iter()
+            .map(|location| location.file.clone())
+            .collect();
+        let included = fs::canonicalize(root.join("common/lib.xml")).unwrap();
+        let extra = fs::canonicalize(root.join("common/extra.yaml")).unwrap();
+        assert_eq!(vec![included, extra, path.clone()], files);
+        assert_eq!(None, locations[1].position);
+        assert!(expanded
+            .root
+            .children()
+            .all(|task| task.attrs().count() == 0));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn include_in_foreign_namespace_is_kept() {
+        let root = fixture(
+            "include_foreign",
+            &[(
+                "main.xml",
+                r#"<tasks xmlns="https://github.com/glecaros/bf" xmlns:x="urn:other">
+                    <copy><x:include file="missing.xml"/></copy>
+                </tasks>"#,
+            )],
+        );
+        let path = root.join("main.xml");
+        let manifest = read_manifest(&path, None).unwrap();
+        let expanded = expand_includes(&manifest, &path, &Runtime::default()).unwrap();
+        let copy = expanded.root.children().next().unwrap();
+        assert!(copy.get_child(ELEMENT_INCLUDE, "urn:other").is_some());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn include_cycle() {
+        let root = fixture(
+            "include_cycle",
+            &[
+                (
+                    "a.xml",
+                    r#"<tasks xmlns="https://github.com/glecaros/bf"><include file="b.xml"/></tasks>"#,
+                ),
+                (
+                    "b.xml",
+                    r#"<tasks xmlns="https://github.com/glecaros/bf"><include file="a.xml"/></tasks>"#,
+                ),
+            ],
+        );
+        let path = root.join("a.xml");
+        let manifest = read_manifest(&path, None).unwrap();
+        let result = expand_includes(&manifest, &path, &Runtime::default());
+        assert!(matches!(result, Err(_)));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn root_validation() {
+        let validate = |xml: &str| validate_root(&xml.parse().unwrap());
+        assert!(matches!(
+            validate(r#"<tasks xmlns="https://github.com/glecaros/bf"/>"#),
+            Ok(_)
+        ));
+        assert!(matches!(
+            validate(r#"<tasks xmlns="https://github.com/glecaros/bf" version="1"/>"#),
+            Ok(_)
+        ));
+        assert!(matches!(
+            validate(r#"<tasks xmlns="https://github.com/glecaros/bf" version="2"/>"#),
+            Err(_)
+        ));
+        assert!(matches!(
+            validate(r#"<tasks xmlns="http://maven.apache.org/POM/4.0.0"/>"#),
+            Err(_)
+        ));
+        assert!(matches!(
+            validate(r#"<project xmlns="https://github.com/glecaros/bf"/>"#),
+            Err(_)
+        ));
+    }
+}
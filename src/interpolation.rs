@@ -1,45 +1,101 @@
 use std::collections::HashMap;
 
-use regex::Regex;
+use clap::ValueEnum;
+use regex::{Captures, Regex};
 
 use crate::{error::Error, internal_error};
 
+const ENV_NAMESPACE: &str = "env.";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum UndefinedEnv {
+    /// Fail when an environment variable is not defined.
+    #[default]
+    Error,
+    /// Replace undefined environment variables with an empty string.
+    Empty,
+}
+
+fn lookup(name: &str, variables: &HashMap<String, String>) -> Option<String> {
+    if let Some(env_name) = name.strip_prefix(ENV_NAMESPACE) {
+        std::env::var(env_name).ok()
+    } else {
+        variables.get(name).cloned()
+    }
+}
+
+fn undefined(name: &str, undefined_env: UndefinedEnv) -> Result<String, Error> {
+    if let Some(env_name) = name.strip_prefix(ENV_NAMESPACE) {
+        match undefined_env {
+            UndefinedEnv::Empty => Ok(String::new()),
+            UndefinedEnv::Error => Err(internal_error!(
+                "Environment variable {} is not defined",
+                env_name
+            )),
+        }
+    } else {
+        Err(internal_error!("Variable {} was not provided", name))
+    }
+}
+
+/// Replaces `{name}` placeholders. Like in a shell, `{name:-default}` falls
+/// back to `default` and `{name:?message}` fails with `message` when the
+/// variable is unset or empty.
+fn resolve(
+    cap: &Captures,
+    variables: &HashMap<String, String>,
+    undefined_env: UndefinedEnv,
+) -> Result<String, Error> {
+    let name = &cap["name"];
+    let value = lookup(name, variables);
+    let modifier = match cap.name("modifier") {
+        Some(modifier) => modifier.as_str(),
+        None => return value.map_or_else(|| undefined(name, undefined_env), Ok),
+    };
+    let argument = &cap["argument"];
+    match value {
+        Some(value) if !value.is_empty() => Ok(value),
+        _ if modifier == "-" => Ok(String::from(argument)),
+        _ if argument.is_empty() => undefined(name, UndefinedEnv::Error),
+        _ => Err(internal_error!("{}: {}", name, argument)),
+    }
+}
+
 pub fn interpolate(
     input_string: &str,
     variables: &HashMap<String, String>,
+    undefined_env: UndefinedEnv,
 ) -> Result<String, Error> {
-    let re = Regex::new("\\{([A-Za-z][A-Za-z0-9_-]*)\\}")?;
-    let mappings: Result<Vec<(Regex, String)>, Error> = re
-        .captures_iter(input_string)
-        .map(|cap| {
-            let var_name = &cap[1];
-            if let Some(value) = variables.get(var_name) {
-                let regex = format!("\\{{{}\\}}", var_name);
-                Ok((Regex::new(&regex)?, value.clone()))
-            } else {
-                Err(internal_error!("Variable {} was not provided", var_name))
+    let re = Regex::new(
+        "\\{(?P<name>env\\.[A-Za-z_][A-Za-z0-9_]*|[A-Za-z][A-Za-z0-9_-]*)(?::(?P<modifier>[-?])(?P<argument>[^}]*))?\\}",
+    )?;
+    let mut error = None;
+    let output = re.replace_all(input_string, |cap: &Captures| {
+        match resolve(cap, variables, undefined_env) {
+            Ok(value) => value,
+            Err(err) => {
+                error.get_or_insert(err);
+                String::new()
             }
-        })
-        .collect();
-    let mappings = mappings?;
-    let output = mappings
-        .iter()
-        .fold(String::from(input_string), |s, (re, val)| {
-            let out = re.replace(&s, val);
-            String::from(out.to_owned())
-        });
-    Ok(output)
+        }
+    });
+    match error {
+        Some(err) => Err(err),
+        None => Ok(output.into_owned()),
+    }
 }
 
 #[cfg(test)]
 mod test {
     use std::collections::HashMap;
 
+    use super::UndefinedEnv;
+
     #[test]
     fn single_variable_present_once() {
         let mut variables = HashMap::new();
         variables.insert(String::from("var"), String::from("value"));
-        let output = super::interpolate("test_{var}_1", &variables);
+        let output = super::interpolate("test_{var}_1", &variables, UndefinedEnv::Error);
         assert!(matches!(output, Ok(_)));
         if let Ok(output) = output {
             assert_eq!("test_value_1", &output);
@@ -50,7 +106,7 @@ mod test {
     fn single_variable_present_twice() {
         let mut variables = HashMap::new();
         variables.insert(String::from("var"), String::from("value"));
-        let output = super::interpolate("test_{var}_some_{var}_2", &variables);
+        let output = super::interpolate("test_{var}_some_{var}_2", &variables, UndefinedEnv::Error);
         assert!(matches!(output, Ok(_)));
         if let Ok(output) = output {
             assert_eq!("test_value_some_value_2", &output);
@@ -62,7 +118,8 @@ mod test {
         let mut variables = HashMap::new();
         variables.insert(String::from("var1"), String::from("value1"));
         variables.insert(String::from("var2"), String::from("value2"));
-        let output = super::interpolate("test_{var1}_some_{var2}_2", &variables);
+        let output =
+            super::interpolate("test_{var1}_some_{var2}_2", &variables, UndefinedEnv::Error);
         assert!(matches!(output, Ok(_)));
         if let Ok(output) = output {
             assert_eq!("test_value1_some_value2_2", &output);
@@ -74,7 +131,11 @@ mod test {
         let mut variables = HashMap::new();
         variables.insert(String::from("var1"), String::from("value1"));
         variables.insert(String::from("var2"), String::from("value2"));
-        let output = super::interpolate("{var2}_{var1}_some_{var1}{var2}_{var1}", &variables);
+        let output = super::interpolate(
+            "{var2}_{var1}_some_{var1}{var2}_{var1}",
+            &variables,
+            UndefinedEnv::Error,
+        );
         assert!(matches!(output, Ok(_)));
         if let Ok(output) = output {
             assert_eq!("value2_value1_some_value1value2_value1", &output);
@@ -84,7 +145,82 @@ mod test {
     #[test]
     fn single_variable_not_present() {
         let variables = HashMap::new();
-        let output = super::interpolate("test_{var1}_some", &variables);
+        let output = super::interpolate("test_{var1}_some", &variables, UndefinedEnv::Error);
         assert!(matches!(output, Err(_)));
     }
+
+    #[test]
+    fn env_variable_present() {
+        let variables = HashMap::new();
+        std::env::set_var("BF_TEST_ENV_PRESENT", "value");
+        let output = super::interpolate(
+            "test_{env.BF_TEST_ENV_PRESENT}_1",
+            &variables,
+            UndefinedEnv::Error,
+        );
+        assert!(matches!(output, Ok(_)));
+        if let Ok(output) = output {
+            assert_eq!("test_value_1", &output);
+        }
+    }
+
+    #[test]
+    fn env_variable_does_not_read_variables() {
+        let mut variables = HashMap::new();
+        variables.insert(String::from("BF_TEST_ENV_SHADOWED"), String::from("value"));
+        let output = super::interpolate(
+            "test_{env.BF_TEST_ENV_SHADOWED}",
+            &variables,
+            UndefinedEnv::Error,
+        );
+        assert!(matches!(output, Err(_)));
+    }
+
+    #[test]
+    fn env_variable_not_present() {
+        let variables = HashMap::new();
+        let output = super::interpolate(
+            "test_{env.BF_TEST_ENV_MISSING}_1",
+            &variables,
+            UndefinedEnv::Error,
+        );
+        assert!(matches!(output, Err(_)));
+        let output = super::interpolate(
+            "test_{env.BF_TEST_ENV_MISSING}_1",
+            &variables,
+            UndefinedEnv::Empty,
+        );
+        assert!(matches!(output, Ok(_)));
+        if let Ok(output) = output {
+            assert_eq!("test__1", &output);
+        }
+    }
+
+    #[test]
+    fn default_value() {
+        let mut variables = HashMap::new();
+        variables.insert(String::from("set"), String::from("value"));
+        variables.insert(String::from("empty"), String::new());
+        let output = super::interpolate(
+            "{set:-other}_{unset:-Release}_{empty:-x}_{env.BF_TEST_ENV_MISSING:-}",
+            &variables,
+            UndefinedEnv::Error,
+        );
+        assert!(matches!(output, Ok(_)));
+        if let Ok(output) = output {
+            assert_eq!("value_Release_x_", &output);
+        }
+    }
+
+    #[test]
+    fn custom_error() {
+        let mut variables = HashMap::new();
+        variables.insert(String::from("set"), String::from("value"));
+        let output = super::interpolate("{set:?must set}", &variables, UndefinedEnv::Error);
+        assert!(matches!(output, Ok(_)));
+        let output = super::interpolate("{arch:?must set arch}", &variables, UndefinedEnv::Error);
+        assert!(matches!(output, Err(ref err) if err.message == "arch: must set arch"));
+        let output = super::interpolate("{arch:?}", &variables, UndefinedEnv::Empty);
+        assert!(matches!(output, Err(ref err) if err.message == "Variable arch was not provided"));
+    }
 }
@@ -1,24 +1,83 @@
+mod condition;
+mod copy;
 mod error;
+mod format;
+mod include;
 mod interpolation;
+mod position;
 pub mod runtime;
 mod task;
+#[cfg(test)]
+mod test_utils;
 mod util;
+mod variables;
 
-use error::Error;
+use error::{Diagnostics, Error, ErrorKind};
 use log::{error, info};
 
-use crate::{task::parse_input_file, util::WorkingDirGuard};
+use crate::{
+    runtime::{Command, Runtime},
+    task::{load_from_directories, parse_input_file, parse_manifest, schema, SkippedTask, Task},
+    util::WorkingDirGuard,
+};
 
 fn execute() -> Result<(), Error> {
-    let runtime = runtime::parse_from_cli();
+    match runtime::parse_from_cli() {
+        Command::Run(runtime) => run(runtime),
+        Command::Validate(runtime) => validate(runtime),
+        Command::Schema(args) => {
+            let descriptors = load_from_directories(args.tasks_dir)
+                .map_err(|e| e.with_kind(ErrorKind::Validation))?;
+            println!("{}", schema(&descriptors)?);
+            Ok(())
+        }
+    }
+}
+
+fn run(mut runtime: Runtime) -> Result<(), Error> {
     info!("runtime: {:?}", &runtime);
-    let tasks = parse_input_file(&runtime)?;
+    let tasks = parse_input_file(&mut runtime)?;
     info!("tasks {:?}", &tasks);
     info!("File parsed successfully, found {} task(s)", tasks.len());
+    if runtime.dry_run {
+        info!("Dry run, no task will be executed");
+        for task in &tasks {
+            for operation in task.describe() {
+                println!("{}", operation);
+            }
+        }
+        return Ok(());
+    }
+    let _guard = WorkingDirGuard::new(&runtime.working_directory)?;
+    for task in &tasks {
+        task.run().map_err(|e| e.with_kind(ErrorKind::Execution))?;
+    }
+    let skipped: Vec<&SkippedTask> = tasks.iter().filter_map(Task::skipped).collect();
+    info!(
+        "Ran {} task(s), skipped {}",
+        tasks.len() - skipped.len(),
+        skipped.len()
+    );
+    for task in skipped {
+        info!("  skipped {}", task.summary());
+    }
+    Ok(())
+}
+
+fn validate(mut runtime: Runtime) -> Result<(), Error> {
+    let mut diagnostics = Diagnostics::default();
+    let tasks = parse_manifest(&mut runtime, &mut diagnostics)?;
     let _guard = WorkingDirGuard::new(&runtime.working_directory)?;
+    let mut produced = Vec::new();
     for task in &tasks {
-        task.run()?;
+        for problem in task.validate(&mut produced) {
+            diagnostics.push(problem);
+        }
     }
+    diagnostics
+        .finish(())
+        .map_err(|e| e.with_kind(ErrorKind::Validation))?;
+    info!("Manifest is valid, found {} task(s)", tasks.len());
     Ok(())
 }
 
@@ -35,7 +94,8 @@ pub fn main() {
         Ok(_) => info!("Execution completed successfully"),
         Err(err) => {
             error!("Execution failed.");
-            error!("Error: {}", err.message);
+            error!("{}", err.report());
+            std::process::exit(err.exit_code());
         }
     }
 }
@@ -0,0 +1,165 @@
+use std::path::Path;
+
+use minidom::Element;
+use quick_xml::{events::Event, Reader};
+
+use crate::error::Location;
+
+/// Where the elements of a manifest were read from. minidom does not keep
+/// track of this, so it is laid out like the element tree instead: one entry
+/// per element, with the entries of its child elements in document order.
+#[derive(Debug, Clone, Default)]
+pub struct Positions {
+    pub location: Option<Location>,
+    pub children: Vec<Positions>,
+}
+
+impl Positions {
+    fn build(element: &Element, locations: &mut impl Iterator<Item = Location>) -> Positions {
+        Positions {
+            location: locations.next(),
+            children: element
+                .children()
+                .map(|child| Positions::build(child, locations))
+                .collect(),
+        }
+    }
+
+    /// Positions of `root` and its descendants, parsed from `contents`, read
+    /// from `file`.
+    pub fn parse(contents: &str, file: &Path, root: &Element) -> Positions {
+        let mut locations = element_offsets(contents)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|offset| location_at(contents, file, offset));
+        Positions::build(root, &mut locations)
+    }
+
+    /// Locates `root` and its descendants in `file`, for formats that do not
+    /// give the line and column of each element.
+    pub fn file(file: &Path, root: &Element) -> Positions {
+        let location = Location {
+            file: file.to_path_buf(),
+            position: None,
+        };
+        Positions::build(root, &mut std::iter::repeat(location))
+    }
+
+    /// Where `element` was read from, if it is `root`, which these positions
+    /// describe, or one of its descendants.
+    pub fn find(&self, root: &Element, element: &Element) -> Option<Location> {
+        if std::ptr::eq(root, element) {
+            return self.location.clone();
+        }
+        root.children()
+            .zip(&self.children)
+            .find_map(|(child, positions)| positions.find(child, element))
+    }
+}
+
+fn location_at(contents: &str, file: &Path, offset: usize) -> Location {
+    let before = &contents[..offset.min(contents.len())];
+    let line_start = before.rfind('\n').map(|index| index + 1).unwrap_or(0);
+    let line = before.matches('\n').count() + 1;
+    let column = before[line_start..].chars().count() + 1;
+    Location {
+        file: file.to_path_buf(),
+        position: Some((line, column)),
+    }
+}
+
+/// Offsets of every element start tag, in document order, or the offset
+/// where the document stops being well formed.
+fn element_offsets(contents: &str) -> Result<Vec<usize>, usize> {
+    let mut reader = Reader::from_str(contents);
+    let mut buffer = Vec::new();
+    let mut offsets = Vec::new();
+    loop {
+        let offset = reader.buffer_position();
+        match reader.read_event(&mut buffer) {
+            Ok(Event::Start(_)) | Ok(Event::Empty(_)) => offsets.push(offset),
+            Ok(Event::Eof) => break,
+            Err(_) => return Err(reader.buffer_position()),
+            _ => {}
+        }
+        buffer.clear();
+    }
+    Ok(offsets)
+}
+
+/// Best guess at where a document that minidom rejected went wrong: where the
+/// XML stops being well formed, or else the root element, which is where
+/// namespace problems are reported.
+pub fn syntax_error_location(contents: &str, file: &Path) -> Option<Location> {
+    let offset = match element_offsets(contents) {
+        Err(offset) => offset,
+        Ok(offsets) => *offsets.first()?,
+    };
+    Some(location_at(contents, file, offset))
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::{Path, PathBuf};
+
+    use minidom::Element;
+
+    use crate::error::Location;
+
+    use super::{syntax_error_location, Positions};
+
+    fn location(line: usize, column: usize) -> Option<Location> {
+        Some(Location {
+            file: PathBuf::from("bf.xml"),
+            position: Some((line, column)),
+        })
+    }
+
+    #[test]
+    fn positions_follow_document_order() {
+        const MANIFEST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<tasks xmlns="https://github.com/glecaros/bf">
+  <copy>
+    <item>a.so</item>
+      <item/>
+  </copy>
+</tasks>"#;
+        let root: Element = MANIFEST.parse().unwrap();
+        let positions = Positions::parse(MANIFEST, Path::new("bf.xml"), &root);
+        let copy = root.children().next().unwrap();
+        let items: Vec<Option<Location>> = copy
+            .children()
+            .map(|item| positions.find(&root, item))
+            .collect();
+        assert_eq!(location(2, 1), positions.find(&root, &root));
+        assert_eq!(location(3, 3), positions.find(&root, copy));
+        assert_eq!(vec![location(4, 5), location(5, 7)], items);
+        assert_eq!(None, positions.find(&root, &copy.clone()));
+    }
+
+    #[test]
+    fn positions_without_lines() {
+        let root: Element = r#"<tasks xmlns="https://github.com/glecaros/bf">
+            <copy><item>a.so</item></copy>
+        </tasks>"#
+            .parse()
+            .unwrap();
+        let positions = Positions::file(Path::new("bf.yaml"), &root);
+        let item = root.children().next().unwrap().children().next().unwrap();
+        let expected = Location {
+            file: PathBuf::from("bf.yaml"),
+            position: None,
+        };
+        assert_eq!(Some(expected), positions.find(&root, item));
+    }
+
+    #[test]
+    fn syntax_error_position() {
+        const MANIFEST: &str = r#"<tasks xmlns="https://github.com/glecaros/bf">
+  <copy>
+  </cpy>
+</tasks>"#;
+        let location = syntax_error_location(MANIFEST, Path::new("bf.xml")).unwrap();
+        assert_eq!(Some(3), location.position.map(|(line, _)| line));
+    }
+}
@@ -1,18 +1,49 @@
-use clap::Parser;
-use std::{error::Error, path::PathBuf};
+use bf_codegen::command::TaskDescriptor;
+use clap::{Args, FromArgMatches, Subcommand};
+use std::{error::Error, path::PathBuf, rc::Rc};
 
-pub fn parse_from_cli() -> Runtime {
-    Runtime::parse()
+use crate::{format::Format, interpolation::UndefinedEnv};
+
+pub const VERSION: &str = "0.2.0";
+
+/// Running a manifest is the default when no subcommand is given, so the run
+/// arguments are accepted both at the top level and under `bf run`.
+pub fn parse_from_cli() -> Command {
+    let cli = clap::Command::new("bf")
+        .version(VERSION)
+        .author(
+            "Gerardo Lecaros <gerardo.lecaros.e@gmail.com>, Jose Alvarez <jp.alvarezl@gmail.com>",
+        )
+        .about("Build Fairy CLI")
+        .args_conflicts_with_subcommands(true)
+        .subcommand_negates_reqs(true);
+    let matches = Command::augment_subcommands(Runtime::augment_args(cli)).get_matches();
+    let command = match matches.subcommand() {
+        Some(_) => Command::from_arg_matches(&matches),
+        None => Runtime::from_arg_matches(&matches).map(Command::Run),
+    };
+    command.unwrap_or_else(|error| error.exit())
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run the tasks in a manifest
+    Run(Runtime),
+    /// Check a manifest without running any task
+    Validate(Runtime),
+    /// Print the XSD schema describing manifests
+    Schema(SchemaArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct SchemaArgs {
+    #[arg(
+        long,
+        value_hint = clap::ValueHint::DirPath)]
+    pub tasks_dir: Option<PathBuf>,
 }
 
-#[derive(Debug, Parser)]
-#[command(
-    name = "bf",
-    version = "0.2.0",
-    author = "Gerardo Lecaros <gerardo.lecaros.e@gmail.com>, Jose Alvarez <jp.alvarezl@gmail.com>",
-    about = "Build Fairy CLI",
-    long_about = "None"
-)]
+#[derive(Debug, Args)]
 pub struct Runtime {
     #[arg(
         short='i',
@@ -48,6 +79,20 @@ pub struct Runtime {
         long,
         value_hint = clap::ValueHint::DirPath)]
     pub destination_base: Option<PathBuf>,
+
+    #[arg(long, value_enum, default_value_t = UndefinedEnv::Error)]
+    pub undefined_env: UndefinedEnv,
+
+    #[arg(long, value_enum)]
+    pub format: Option<Format>,
+
+    #[arg(
+        long,
+        value_hint = clap::ValueHint::DirPath)]
+    pub tasks_dir: Option<PathBuf>,
+
+    #[arg(skip)]
+    pub task_descriptors: Vec<Rc<TaskDescriptor>>,
 }
 
 // https://github.com/clap-rs/clap/discussions/4291#discussioncomment-3764804
@@ -68,11 +113,15 @@ impl Default for Runtime {
     fn default() -> Self {
         Runtime {
             input: PathBuf::new(),
-            working_directory: PathBuf::new(),
+            working_directory: PathBuf::from("."),
             variables: Vec::new(),
             dry_run: false,
             source_base: None,
             destination_base: None,
+            undefined_env: UndefinedEnv::Error,
+            format: None,
+            tasks_dir: None,
+            task_descriptors: Vec::new(),
         }
     }
 }
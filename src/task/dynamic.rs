@@ -0,0 +1,804 @@
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    fs::{self, File},
+    path::{Path, PathBuf},
+    process::Command as Process,
+    rc::Rc,
+};
+
+use bf_codegen::{
+    command::{
+        Command, CommandLineDescriptor, ElementDescriptor, GroupSetting, ParameterDescriptor,
+        ParameterRole, ParameterType, TaskDescriptor,
+    },
+    command_parser::CommandPart,
+};
+use minidom::Element;
+
+use crate::{
+    error::{Diagnostics, Error, ErrorContext},
+    internal_error,
+    runtime::Runtime,
+    util::{
+        append_list, check_item, evaluate_condition_from_element, expand_glob, glob_required,
+        interpolate_attribute, interpolate_list, interpolate_text, parse_bool, parse_enum,
+        parse_int, parse_octal_mode, resolve_destination, select_command_variant, ApplyPrefix,
+        Exclusions, FileMode, VariantKey, ELEMENT_EXCLUDE,
+    },
+};
+
+use super::BUILTIN_TASKS;
+
+/// Directory next to the manifest that is searched for task descriptors.
+const TASKS_DIR: &str = "bf-tasks";
+
+#[derive(Debug, Clone)]
+enum Value {
+    Path(PathBuf),
+    String(String),
+    Bool(bool),
+    Int(i64),
+    Mode(FileMode),
+    List(Vec<Value>),
+}
+
+impl Value {
+    fn parse(parameter_type: &ParameterType, name: &str, value: String) -> Result<Value, Error> {
+        match parameter_type {
+            ParameterType::Path => Ok(Value::Path(PathBuf::from(value))),
+            ParameterType::String => Ok(Value::String(value)),
+            ParameterType::Bool => parse_bool(name, &value).map(Value::Bool),
+            ParameterType::Int => parse_int(name, &value).map(Value::Int),
+            ParameterType::OctalMode => parse_octal_mode(name, &value).map(Value::Mode),
+            ParameterType::Enum(values) => {
+                let allowed: Vec<&str> = values.iter().map(String::as_str).collect();
+                parse_enum(name, value, &allowed).map(Value::String)
+            }
+            ParameterType::List(inner) => {
+                Value::parse(inner, name, value).map(|value| Value::List(vec![value]))
+            }
+        }
+    }
+
+    fn path(&self) -> Option<&PathBuf> {
+        match self {
+            Value::Path(path) => Some(path),
+            _ => None,
+        }
+    }
+
+    fn arguments(&self) -> Vec<OsString> {
+        match self {
+            Value::Path(path) => vec![path.clone().into_os_string()],
+            Value::String(value) => vec![OsString::from(value)],
+            Value::Bool(value) => vec![OsString::from(value.to_string())],
+            Value::Int(value) => vec![OsString::from(value.to_string())],
+            Value::Mode(mode) => vec![OsString::from(mode.to_string())],
+            Value::List(values) => values.iter().flat_map(Value::arguments).collect(),
+        }
+    }
+}
+
+fn read(
+    parameter: &ParameterDescriptor,
+    name: &str,
+    element: &Element,
+    runtime: &Runtime,
+) -> Result<Option<Value>, Error> {
+    match &parameter.parameter_type {
+        ParameterType::List(inner) => interpolate_list(name, element, runtime)?
+            .map(|values| {
+                values
+                    .into_iter()
+                    .map(|value| Value::parse(inner, &parameter.name, value))
+                    .collect::<Result<Vec<Value>, Error>>()
+                    .map(Value::List)
+            })
+            .transpose(),
+        parameter_type => interpolate_attribute(name, element, runtime)?
+            .map(|value| Value::parse(parameter_type, &parameter.name, value))
+            .transpose(),
+    }
+}
+
+fn apply_prefix(value: Option<Value>, prefix: &Option<PathBuf>) -> Option<Value> {
+    value
+        .and_then(|value| value.path().cloned())
+        .apply_prefix(prefix)
+        .map(Value::Path)
+}
+
+fn apply_prefix_if_set(value: Option<Value>, prefix: &Option<PathBuf>) -> Option<Value> {
+    value
+        .and_then(|value| value.path().cloned())
+        .apply_prefix_if_set(prefix)
+        .map(Value::Path)
+}
+
+fn append(parent: Option<&Value>, value: Option<Value>) -> Option<Value> {
+    let parent = match parent {
+        Some(Value::List(values)) => Some(values),
+        _ => None,
+    };
+    let values = value.map(|value| match value {
+        Value::List(values) => values,
+        value => vec![value],
+    });
+    append_list(parent, values).map(Value::List)
+}
+
+fn find_role(element: &ElementDescriptor, role: ParameterRole) -> Option<&ParameterDescriptor> {
+    element
+        .attributes
+        .iter()
+        .find(|attribute| attribute.role == Some(role))
+}
+
+fn base(role: ParameterRole, runtime: &Runtime) -> &Option<PathBuf> {
+    match role {
+        ParameterRole::Source => &runtime.source_base,
+        ParameterRole::Destination => &runtime.destination_base,
+    }
+}
+
+fn is_group_list(element: &ElementDescriptor, name: &str) -> bool {
+    element.attributes.iter().any(|attribute| {
+        attribute.name == name
+            && attribute.parameter_type.is_list()
+            && !matches!(attribute.allow_group, GroupSetting::None)
+    })
+}
+
+#[derive(Debug)]
+struct Group {
+    values: HashMap<String, Value>,
+    exclude: Exclusions,
+}
+
+impl Group {
+    fn create(
+        descriptor: &ElementDescriptor,
+        element: &Element,
+        parent: Option<&Group>,
+        runtime: &Runtime,
+    ) -> Result<Group, Error> {
+        let mut values = HashMap::new();
+        for parameter in &descriptor.attributes {
+            let name = &parameter.name;
+            let inherited = parent.and_then(|group| group.values.get(name));
+            let value = match parameter.allow_group {
+                GroupSetting::None => continue,
+                GroupSetting::Inherit => {
+                    read(parameter, name, element, runtime)?.or_else(|| inherited.cloned())
+                }
+                GroupSetting::Prefix | GroupSetting::InheritPrefix => {
+                    let value = read(parameter, name, element, runtime)?;
+                    match (parent, parameter.role) {
+                        (Some(group), _) => apply_prefix(value, &group.path(name)),
+                        (None, Some(role)) => apply_prefix(value, base(role, runtime)),
+                        (None, None) => value,
+                    }
+                }
+                GroupSetting::Append => append(inherited, read(parameter, name, element, runtime)?),
+            };
+            if let Some(value) = value {
+                values.insert(name.clone(), value);
+            }
+        }
+        let exclude = Exclusions::create(element, parent.map(|group| &group.exclude), runtime)?;
+        Ok(Group { values, exclude })
+    }
+
+    fn path(&self, name: &str) -> Option<PathBuf> {
+        self.values.get(name).and_then(Value::path).cloned()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Item {
+    values: HashMap<String, Value>,
+}
+
+impl Item {
+    fn create(
+        descriptor: &ElementDescriptor,
+        element: &Element,
+        parent: &Group,
+        runtime: &Runtime,
+    ) -> Result<Item, Error> {
+        let mut values = HashMap::new();
+        for parameter in &descriptor.attributes {
+            let name = &parameter.name;
+            let mut value = read(parameter, name, element, runtime)?;
+            if value.is_none() && descriptor.text.as_ref() == Some(name) {
+                value = interpolate_text(element, runtime)?
+                    .map(|text| Value::parse(&parameter.parameter_type, name, text))
+                    .transpose()?;
+            }
+            if let (None, Some(alias)) = (&value, &parameter.defaults_to) {
+                value = read(parameter, alias, element, runtime)?;
+            }
+            let inherited = parent.values.get(name);
+            let value = match parameter.allow_group {
+                GroupSetting::None => value,
+                GroupSetting::Inherit => value.or_else(|| inherited.cloned()),
+                GroupSetting::Prefix => apply_prefix_if_set(value, &parent.path(name)),
+                GroupSetting::InheritPrefix => apply_prefix(value, &parent.path(name)),
+                GroupSetting::Append => append(inherited, value),
+            };
+            match value {
+                Some(value) => {
+                    values.insert(name.clone(), value);
+                }
+                None if parameter.required => {
+                    return Err(internal_error!("Missing required value: '{}'", name));
+                }
+                None => {}
+            }
+        }
+        Ok(Item { values })
+    }
+
+    fn path(&self, name: &str) -> Option<&PathBuf> {
+        self.values.get(name).and_then(Value::path)
+    }
+
+    fn expand(
+        self,
+        descriptor: &ElementDescriptor,
+        element: &Element,
+        parent: &Group,
+    ) -> Result<Vec<Item>, Error> {
+        let source = match find_role(descriptor, ParameterRole::Source) {
+            Some(source) => source,
+            None => return Ok(vec![self]),
+        };
+        let root = Some(PathBuf::new())
+            .apply_prefix(&parent.path(&source.name))
+            .unwrap_or_default();
+        let pattern = match self.path(&source.name) {
+            Some(pattern) => pattern.clone(),
+            None => return Ok(vec![self]),
+        };
+        let destination = find_role(descriptor, ParameterRole::Destination);
+        let matches = match expand_glob(&pattern, &root, glob_required(element)?, &parent.exclude)?
+        {
+            Some(matches) => matches,
+            None => {
+                let mut item = self;
+                if let Some(destination) = destination {
+                    if let Some(path) = item.path(&destination.name) {
+                        let path = Value::Path(resolve_destination(&pattern, path));
+                        item.values.insert(destination.name.clone(), path);
+                    }
+                }
+                return Ok(vec![item]);
+            }
+        };
+        let items = matches
+            .into_iter()
+            .map(|(path, relative)| {
+                let mut item = self.clone();
+                item.values.insert(source.name.clone(), Value::Path(path));
+                if let Some(destination) = destination {
+                    if let Some(path) = item.path(&destination.name) {
+                        let path = Value::Path(path.join(relative));
+                        item.values.insert(destination.name.clone(), path);
+                    }
+                }
+                item
+            })
+            .collect();
+        Ok(items)
+    }
+}
+
+/// A command line task whose descriptor was loaded when `bf` started rather
+/// than compiled in. Parameters and groups behave as in generated tasks.
+#[derive(Debug)]
+pub struct DynamicTask {
+    descriptor: Rc<TaskDescriptor>,
+    items: Vec<Item>,
+    variant: usize,
+}
+
+fn command_line_descriptor(descriptor: &TaskDescriptor) -> &CommandLineDescriptor {
+    match &descriptor.command {
+        Command::CommandLine(command_line) => command_line,
+        Command::Snippet(_) => unreachable!("Snippet descriptors are rejected when loaded"),
+    }
+}
+
+fn select_variant(descriptor: &TaskDescriptor, runtime: &Runtime) -> Result<usize, Error> {
+    let command_line = command_line_descriptor(descriptor);
+    let variants: Vec<VariantKey> = command_line
+        .variants
+        .iter()
+        .map(|variant| {
+            (
+                variant.os.as_deref(),
+                variant.arch.as_deref(),
+                variant.condition.as_deref(),
+            )
+        })
+        .collect();
+    select_command_variant(
+        &descriptor.name,
+        runtime,
+        &variants,
+        command_line.default.is_some(),
+    )
+}
+
+fn token_arguments(token: &str, item: &Item) -> Vec<OsString> {
+    match token.strip_prefix('$') {
+        Some(name) => item
+            .values
+            .get(name)
+            .map(Value::arguments)
+            .unwrap_or_default(),
+        None => vec![OsString::from(token)],
+    }
+}
+
+/// Optional parts are only added when all their values are set, once per
+/// value of the list they reference, if any.
+fn add_optional_part(call: &mut Process, part: &CommandPart, item: &Item) {
+    let present = part
+        .dependencies
+        .iter()
+        .all(|dependency| item.values.contains_key(dependency));
+    if !present {
+        return;
+    }
+    let list = part
+        .dependencies
+        .iter()
+        .find_map(|dependency| match item.values.get(dependency) {
+            Some(Value::List(values)) => Some((dependency, values)),
+            _ => None,
+        });
+    match list {
+        Some((name, values)) => {
+            for value in values {
+                for token in &part.tokens {
+                    if token.strip_prefix('$') == Some(name.as_str()) {
+                        call.args(value.arguments());
+                    } else {
+                        call.args(token_arguments(token, item));
+                    }
+                }
+            }
+        }
+        None => {
+            for token in &part.tokens {
+                call.args(token_arguments(token, item));
+            }
+        }
+    }
+}
+
+impl DynamicTask {
+    fn context(&self, index: usize, item: &Item) -> ErrorContext {
+        let path = |role| {
+            find_role(&self.descriptor.element, role)
+                .and_then(|parameter| item.path(&parameter.name))
+        };
+        ErrorContext::new(
+            &self.descriptor.name,
+            index,
+            path(ParameterRole::Source),
+            path(ParameterRole::Destination),
+        )
+    }
+
+    fn command_line(&self, item: &Item) -> Process {
+        let details = command_line_descriptor(&self.descriptor)
+            .commands()
+            .nth(self.variant)
+            .expect("Selected command line variants exist");
+        let mut call = Process::new(&details.command_name);
+        for part in &details.parts {
+            if part.optional {
+                add_optional_part(&mut call, part, item);
+            } else {
+                for token in &part.tokens {
+                    call.args(token_arguments(token, item));
+                }
+            }
+        }
+        call
+    }
+
+    fn execute(&self, item: &Item) -> Result<(), Error> {
+        let output = self.command_line(item).output()?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            let std_err = std::str::from_utf8(&output.stderr)?;
+            Err(Error::from(std_err.trim()))
+        }
+    }
+
+    pub fn run(&self) -> Result<(), Error> {
+        for (index, item) in self.items.iter().enumerate() {
+            self.execute(item)
+                .map_err(|error| error.with_context(self.context(index, item)))?;
+        }
+        Ok(())
+    }
+
+    pub fn describe(&self) -> Vec<String> {
+        self.items
+            .iter()
+            .map(|item| format!("{:?}", self.command_line(item)))
+            .collect()
+    }
+
+    pub fn validate(&self, produced: &mut Vec<PathBuf>) -> Vec<Error> {
+        self.items
+            .iter()
+            .enumerate()
+            .filter_map(|(index, item)| check_item(self.context(index, item), produced))
+            .collect()
+    }
+}
+
+fn parse_item(
+    descriptor: &TaskDescriptor,
+    runtime: &Runtime,
+    element: &Element,
+    parent: &Group,
+) -> Result<Vec<Item>, Error> {
+    if evaluate_condition_from_element(runtime, element)? {
+        let item = Item::create(&descriptor.element, element, parent, runtime)?;
+        item.expand(&descriptor.element, element, parent)
+    } else {
+        Ok(Vec::new())
+    }
+}
+
+fn parse_items(
+    descriptor: &TaskDescriptor,
+    runtime: &Runtime,
+    parent: &Element,
+    group: Option<&Group>,
+    diagnostics: &mut Diagnostics,
+) -> Result<Option<Vec<Item>>, Error> {
+    if !evaluate_condition_from_element(runtime, parent)? {
+        return Ok(None);
+    }
+    let group = Group::create(&descriptor.element, parent, group, runtime)?;
+    let mut items = Vec::new();
+    for item in parent.children() {
+        match item.name() {
+            "item" => {
+                let result = parse_item(descriptor, runtime, item, &group);
+                if let Some(mut inner_items) = diagnostics.record(item, result) {
+                    items.append(&mut inner_items);
+                }
+            }
+            "group" => {
+                let result = parse_items(descriptor, runtime, item, Some(&group), diagnostics);
+                if let Some(Some(mut inner_items)) = diagnostics.record(item, result) {
+                    items.append(&mut inner_items);
+                }
+            }
+            ELEMENT_EXCLUDE => {}
+            name if is_group_list(&descriptor.element, name) => {}
+            name => {
+                let error = internal_error!("Invalid element: {}", name);
+                diagnostics.record::<()>(item, Err(error));
+            }
+        }
+    }
+    Ok(Some(items))
+}
+
+pub fn parse_task(
+    runtime: &Runtime,
+    element: &Element,
+    diagnostics: &mut Diagnostics,
+) -> Result<Option<DynamicTask>, Error> {
+    let descriptor = runtime
+        .task_descriptors
+        .iter()
+        .find(|descriptor| descriptor.name == element.name())
+        .ok_or_else(|| internal_error!("Invalid task '{}'", element.name()))?;
+    let items = parse_items(descriptor, runtime, element, None, diagnostics)?;
+    let task = match items {
+        Some(items) => Some(DynamicTask {
+            descriptor: descriptor.clone(),
+            items,
+            variant: select_variant(descriptor, runtime)?,
+        }),
+        None => None,
+    };
+    Ok(task)
+}
+
+fn descriptor_files(directory: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        let extension = path.extension().and_then(|extension| extension.to_str());
+        if matches!(extension, Some("yaml") | Some("yml")) {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn load_descriptor(path: &Path) -> Result<TaskDescriptor, Error> {
+    let descriptor = TaskDescriptor::from_reader(File::open(path)?)?;
+    if let Command::Snippet(_) = descriptor.command {
+        return Err(internal_error!(
+            "Task {} is a snippet task, which has to be built into bf",
+            descriptor.name
+        ));
+    }
+    if BUILTIN_TASKS.contains(&descriptor.name.as_str()) {
+        return Err(internal_error!(
+            "Task {} is already built into bf",
+            descriptor.name
+        ));
+    }
+    Ok(descriptor)
+}
+
+/// Loads the command line task descriptors found in `--tasks-dir` and in the
+/// `bf-tasks` directory next to the manifest.
+pub fn load_task_descriptors(runtime: &Runtime) -> Result<Vec<Rc<TaskDescriptor>>, Error> {
+    let next_to_manifest = runtime
+        .input
+        .parent()
+        .map(|directory| directory.join(TASKS_DIR))
+        .filter(|directory| directory.is_dir());
+    load_from_directories(runtime.tasks_dir.iter().cloned().chain(next_to_manifest))
+}
+
+/// Loads the command line task descriptors found in `directories`, each
+/// directory being read once.
+pub fn load_from_directories(
+    directories: impl IntoIterator<Item = PathBuf>,
+) -> Result<Vec<Rc<TaskDescriptor>>, Error> {
+    let mut unique: Vec<PathBuf> = Vec::new();
+    for directory in directories {
+        let directory = directory.canonicalize().map_err(|e| {
+            internal_error!(
+                "Invalid tasks directory {}: {}",
+                directory.to_string_lossy(),
+                e
+            )
+        })?;
+        if !unique.contains(&directory) {
+            unique.push(directory);
+        }
+    }
+    let mut descriptors: Vec<Rc<TaskDescriptor>> = Vec::new();
+    for directory in unique {
+        for path in descriptor_files(&directory)? {
+            let descriptor = load_descriptor(&path).map_err(|e| {
+                internal_error!(
+                    "Invalid task descriptor {}: {}",
+                    path.to_string_lossy(),
+                    e.message
+                )
+            })?;
+            if descriptors
+                .iter()
+                .any(|known| known.name == descriptor.name)
+            {
+                return Err(internal_error!(
+                    "Task {} in {} is already defined",
+                    descriptor.name,
+                    path.to_string_lossy()
+                ));
+            }
+            descriptors.push(Rc::new(descriptor));
+        }
+    }
+    Ok(descriptors)
+}
+
+#[cfg(test)]
+mod test {
+    use std::rc::Rc;
+
+    use bf_codegen::command::TaskDescriptor;
+    use minidom::Element;
+
+    use crate::{
+        error::{Diagnostics, Error},
+        runtime::Runtime,
+    };
+
+    use super::parse_task;
+
+    /// Generated tasks for the descriptors in `testdata`, which only some of
+    /// their code is exercised from.
+    #[allow(dead_code)]
+    mod generated {
+        include!(concat!(env!("OUT_DIR"), "/test_commands.rs"));
+    }
+
+    const DESCRIPTOR: &str = r#"
+name: cc
+command:
+  command_line:
+    variants:
+      - os: linux
+        arch: aarch64
+        command: aarch64-linux-gnu-gcc -o $output $sources
+    default: cc [-O $level ] [-I $includes ] -o $output $sources
+element:
+  attributes:
+    - name: sources
+      type: list<path>
+      allow_group: none
+      required: true
+    - name: output
+      type: path
+      allow_group: inherit_prefix
+      role: destination
+      required: true
+    - name: includes
+      type: list<path>
+      allow_group: append
+      required: false
+    - name: level
+      type: int
+      allow_group: inherit
+      required: false
+"#;
+
+    const STRIP_DESCRIPTOR: &str = include_str!("../../tasks/strip.yaml");
+    const COMPILE_DESCRIPTOR: &str = include_str!("testdata/compile.yaml");
+
+    fn runtime() -> Runtime {
+        let descriptor = TaskDescriptor::from_reader(DESCRIPTOR.as_bytes()).unwrap();
+        Runtime {
+            task_descriptors: vec![Rc::new(descriptor)],
+            ..Runtime::default()
+        }
+    }
+
+    #[test]
+    fn dynamic_task_follows_group_semantics() {
+        let element: Element = r#"<cc xmlns="https://github.com/glecaros/bf">
+            <group output="out/" includes="include" level="2">
+                <item output="app" sources="main.c;util.c"><includes>vendor</includes></item>
+                <item output="tool" level="3"><sources>tool.c</sources></item>
+            </group>
+        </cc>"#
+            .parse()
+            .unwrap();
+        let mut diagnostics = Diagnostics::default();
+        let task = parse_task(&runtime(), &element, &mut diagnostics)
+            .unwrap()
+            .unwrap();
+        let expected = [
+            r#""cc" "-O" "2" "-I" "include" "-I" "vendor" "-o" "out/app" "main.c" "util.c""#,
+            r#""cc" "-O" "3" "-I" "include" "-o" "out/tool" "tool.c""#,
+        ];
+        assert_eq!(expected.to_vec(), task.describe());
+    }
+
+    #[test]
+    fn dynamic_task_selects_variant() {
+        let element: Element = r#"<cc xmlns="https://github.com/glecaros/bf">
+            <item output="app" sources="main.c"/>
+        </cc>"#
+            .parse()
+            .unwrap();
+        let mut runtime = runtime();
+        for (name, value) in [("os", "linux"), ("arch", "aarch64")] {
+            runtime
+                .variables
+                .push((String::from(name), String::from(value)));
+        }
+        let mut diagnostics = Diagnostics::default();
+        let task = parse_task(&runtime, &element, &mut diagnostics)
+            .unwrap()
+            .unwrap();
+        let expected = [r#""aarch64-linux-gnu-gcc" "-o" "app" "main.c""#];
+        assert_eq!(expected.to_vec(), task.describe());
+    }
+
+    /// Parses `manifest` with `generated` and with a dynamic task loaded from
+    /// `descriptor`, which `generated` was built from, so both implementations
+    /// can be compared.
+    fn compare(
+        descriptor: &str,
+        generated: impl Fn(&Runtime, &Element, &mut Diagnostics) -> Result<Option<Vec<String>>, Error>,
+        manifest: &str,
+        variables: &[(&str, &str)],
+    ) -> (String, String) {
+        let element: Element = manifest.parse().unwrap();
+        let descriptor = TaskDescriptor::from_reader(descriptor.as_bytes()).unwrap();
+        let mut runtime = Runtime {
+            task_descriptors: vec![Rc::new(descriptor)],
+            ..Runtime::default()
+        };
+        for (name, value) in variables {
+            runtime
+                .variables
+                .push((String::from(*name), String::from(*value)));
+        }
+        let mut diagnostics = Diagnostics::default();
+        let generated = generated(&runtime, &element, &mut diagnostics);
+        let generated = format!("{:?} {:?}", generated, diagnostics.finish(()));
+        let mut diagnostics = Diagnostics::default();
+        let dynamic = parse_task(&runtime, &element, &mut diagnostics)
+            .map(|task| task.map(|task| task.describe()));
+        let dynamic = format!("{:?} {:?}", dynamic, diagnostics.finish(()));
+        (generated, dynamic)
+    }
+
+    fn parse_both(manifest: &str, variables: &[(&str, &str)]) -> (String, String) {
+        let generated = |runtime: &Runtime, element: &Element, diagnostics: &mut Diagnostics| {
+            crate::task::strip::parse_task(runtime, element, diagnostics)
+                .map(|task| task.map(|task| task.describe()))
+        };
+        compare(STRIP_DESCRIPTOR, generated, manifest, variables)
+    }
+
+    #[test]
+    fn dynamic_task_matches_generated_task() {
+        let manifest = r#"<strip xmlns="https://github.com/glecaros/bf">
+            <group source="build/" destination="out/" keep_symbols="main">
+                <item keep_symbols="init"><remove_sections>.comment</remove_sections>app</item>
+                <group source="lib/" remove_sections=".note">
+                    <item destination="libs/">libfoo.so</item>
+                    <item condition="os == 'windows'">skipped.dll</item>
+                </group>
+            </group>
+            <item>tool</item>
+        </strip>"#;
+        for os in ["linux", "windows", "macos"] {
+            let (generated, dynamic) = parse_both(manifest, &[("os", os)]);
+            assert!(generated.starts_with("Ok(Some("), "{}", generated);
+            assert_eq!(generated, dynamic);
+        }
+    }
+
+    #[test]
+    fn dynamic_task_reports_generated_task_errors() {
+        let manifests = [
+            r#"<strip xmlns="https://github.com/glecaros/bf"><item destination="out/"/></strip>"#,
+            r#"<strip xmlns="https://github.com/glecaros/bf"><unknown/></strip>"#,
+            r#"<strip xmlns="https://github.com/glecaros/bf" condition="os == 'linux'"><item>a</item></strip>"#,
+        ];
+        for manifest in manifests {
+            let (generated, dynamic) = parse_both(manifest, &[("os", "windows")]);
+            assert_eq!(generated, dynamic);
+        }
+    }
+
+    #[test]
+    fn dynamic_task_matches_generated_list_defaults() {
+        let manifest = r#"<compile xmlns="https://github.com/glecaros/bf">
+            <group output="out/" includes="include">
+                <item sources="main.c" headers="vendor">app</item>
+                <item sources="tool.c" includes="tools" headers="vendor">tool</item>
+                <item sources="lib.c">lib</item>
+            </group>
+        </compile>"#;
+        let generated = |runtime: &Runtime, element: &Element, diagnostics: &mut Diagnostics| {
+            generated::compile::parse_task(runtime, element, diagnostics)
+                .map(|task| task.map(|task| task.describe()))
+        };
+        let (generated, dynamic) = compare(COMPILE_DESCRIPTOR, generated, manifest, &[]);
+        assert!(generated.contains("vendor"), "{}", generated);
+        assert_eq!(generated, dynamic);
+    }
+
+    #[test]
+    fn unknown_task() {
+        let element: Element = r#"<c xmlns="https://github.com/glecaros/bf"/>"#.parse().unwrap();
+        let result = parse_task(&runtime(), &element, &mut Diagnostics::default());
+        assert!(matches!(result, Err(_)));
+    }
+}
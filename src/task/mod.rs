@@ -1,16 +1,323 @@
+mod dynamic;
+
+use std::{path::PathBuf, rc::Rc};
+
+use bf_codegen::{command::TaskDescriptor, generate_schema};
+
+use log::info;
 use minidom::Element;
-use std::{fs::File, io::Read};
 
-use crate::{error::Error, runtime::Runtime, util::WorkingDirGuard};
+use crate::{
+    error::{Diagnostics, Error, ErrorKind, Location},
+    include::{expand_includes, read_manifest},
+    internal_error,
+    runtime::Runtime,
+    util::{interpolate_attribute, WorkingDirGuard},
+    variables::{builtin_variables, Declarations, ELEMENT_VARIABLES},
+};
+
+pub use dynamic::load_from_directories;
+
+use dynamic::{load_task_descriptors, DynamicTask};
 
 include!(concat!(env!("OUT_DIR"), "/commands.rs"));
 
-pub fn parse_input_file(runtime: &Runtime) -> Result<Vec<Task>, Error> {
-    let input = File::open(&runtime.input).and_then(|mut file| {
-        let mut contents = String::new();
-        file.read_to_string(&mut contents)?;
-        Ok(contents)
+const ATTR_SKIP_REASON: &str = "skip_reason";
+
+/// A task left out because its `condition` evaluated to false.
+#[derive(Debug)]
+pub struct SkippedTask {
+    pub name: String,
+    pub reason: Option<String>,
+    pub location: Option<Location>,
+}
+
+impl SkippedTask {
+    fn new(
+        element: &Element,
+        runtime: &Runtime,
+        location: Option<Location>,
+    ) -> Result<SkippedTask, Error> {
+        Ok(SkippedTask {
+            name: String::from(element.name()),
+            reason: interpolate_attribute(ATTR_SKIP_REASON, element, runtime)?,
+            location,
+        })
+    }
+
+    pub fn summary(&self) -> String {
+        let location = self
+            .location
+            .as_ref()
+            .map(|location| format!(" ({})", location))
+            .unwrap_or_default();
+        match &self.reason {
+            Some(reason) => format!("{}{}: {}", self.name, location, reason),
+            None => format!("{}{}", self.name, location),
+        }
+    }
+
+    pub fn run(&self) -> Result<(), Error> {
+        info!("Skipping task {}", self.summary());
+        Ok(())
+    }
+
+    pub fn describe(&self) -> Vec<String> {
+        vec![format!("skip {}", self.summary())]
+    }
+
+    pub fn validate(&self, _produced: &mut Vec<PathBuf>) -> Vec<Error> {
+        Vec::new()
+    }
+}
+
+impl Task {
+    pub fn skipped(&self) -> Option<&SkippedTask> {
+        match self {
+            Task::Skipped(skipped) => Some(skipped),
+            _ => None,
+        }
+    }
+}
+
+pub fn parse_input_file(runtime: &mut Runtime) -> Result<Vec<Task>, Error> {
+    let mut diagnostics = Diagnostics::default();
+    let tasks = parse_manifest(runtime, &mut diagnostics)?;
+    diagnostics
+        .finish(tasks)
+        .map_err(|e| e.with_kind(ErrorKind::Validation))
+}
+
+/// Parses the manifest, collecting problems found in individual tasks into
+/// `diagnostics` and returning the tasks that parsed successfully.
+pub fn parse_manifest(
+    runtime: &mut Runtime,
+    diagnostics: &mut Diagnostics,
+) -> Result<Vec<Task>, Error> {
+    let mut manifest = read_manifest(&runtime.input, runtime.format)?;
+    let namespace = manifest.root.ns();
+    let mut variables = builtin_variables(runtime)?;
+    if let Some(declarations) = manifest.remove_child(ELEMENT_VARIABLES, namespace.as_str()) {
+        Declarations::parse(&declarations.root, &declarations.positions)
+            .and_then(|parsed| parsed.apply(runtime, &variables))
+            .map_err(|e| {
+                e.with_kind(ErrorKind::Validation)
+                    .at(declarations.positions.location.clone())
+            })?;
+    }
+    variables.append(&mut runtime.variables);
+    runtime.variables = variables;
+    let manifest = expand_includes(&manifest, &runtime.input, runtime).map_err(|e| {
+        let kind = e.kind.unwrap_or(ErrorKind::Validation);
+        e.with_kind(kind)
     })?;
+    runtime.task_descriptors =
+        load_task_descriptors(runtime).map_err(|e| e.with_kind(ErrorKind::Validation))?;
     let _guard = WorkingDirGuard::new(&runtime.working_directory)?;
-    parse_input(runtime, &input)
+    let manifest = Rc::new(manifest);
+    diagnostics.locate_in(manifest.clone());
+    Ok(parse_input(runtime, &manifest.root, diagnostics))
+}
+
+fn builtin_descriptors() -> Result<Vec<TaskDescriptor>, Error> {
+    BUILTIN_DESCRIPTORS
+        .iter()
+        .map(|descriptor| {
+            TaskDescriptor::from_reader(descriptor.as_bytes())
+                .map_err(|e| internal_error!("Invalid built-in task descriptor: {}", e))
+        })
+        .collect()
+}
+
+/// The XSD schema describing manifests that use the built-in tasks and
+/// `descriptors`.
+pub fn schema(descriptors: &[Rc<TaskDescriptor>]) -> Result<String, Error> {
+    if descriptors.is_empty() {
+        return Ok(String::from(SCHEMA));
+    }
+    let builtin = builtin_descriptors()?;
+    let tasks: Vec<&TaskDescriptor> = builtin
+        .iter()
+        .chain(descriptors.iter().map(Rc::as_ref))
+        .collect();
+    Ok(generate_schema(&tasks))
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, path::Path, rc::Rc};
+
+    use bf_codegen::command::TaskDescriptor;
+    use minidom::Element;
+
+    use crate::{
+        error::{Diagnostics, Error},
+        runtime::Runtime,
+        test_utils::fixture,
+    };
+
+    use super::{builtin_descriptors, generate_schema, schema, Task, SCHEMA};
+
+    fn parse_input(runtime: &Runtime, root: &Element) -> Result<Vec<Task>, Error> {
+        let mut diagnostics = Diagnostics::default();
+        let tasks = super::parse_input(runtime, root, &mut diagnostics);
+        diagnostics.finish(tasks)
+    }
+
+    #[test]
+    fn task_condition_skips_task() {
+        let root: Element = r#"<tasks xmlns="https://github.com/glecaros/bf">
+            <copy condition="os == 'linux'" skip_reason="only needed on {os}">
+                <item destination="out/">a.so</item>
+            </copy>
+            <copy condition="os == 'windows'">
+                <item destination="out/">b.so</item>
+            </copy>
+        </tasks>"#
+            .parse()
+            .unwrap();
+        let mut runtime = Runtime::default();
+        runtime
+            .variables
+            .push((String::from("os"), String::from("windows")));
+        let tasks = parse_input(&runtime, &root).unwrap();
+        assert_eq!(2, tasks.len());
+        let skipped = tasks[0].skipped().unwrap();
+        assert_eq!("copy", skipped.name);
+        assert_eq!(Some("only needed on windows"), skipped.reason.as_deref());
+        assert!(tasks[1].skipped().is_none());
+    }
+
+    #[test]
+    fn parse_input_keeps_valid_tasks() {
+        let root: Element = r#"<tasks xmlns="https://github.com/glecaros/bf">
+            <copy>
+                <itme destination="out/">a.so</itme>
+            </copy>
+            <copy>
+                <item destination="out/">b.so</item>
+            </copy>
+        </tasks>"#
+            .parse()
+            .unwrap();
+        let mut diagnostics = Diagnostics::default();
+        let tasks = super::parse_input(&Runtime::default(), &root, &mut diagnostics);
+        assert_eq!(2, tasks.len());
+        assert_eq!(vec!["copy b.so -> out/b.so"], tasks[1].describe());
+        let error = diagnostics.finish(()).unwrap_err();
+        assert_eq!("Invalid element: itme", error.message);
+    }
+
+    #[test]
+    fn destination_directory_resolved_when_parsed() {
+        let root: Element = r#"<tasks xmlns="https://github.com/glecaros/bf">
+            <copy>
+                <group source="srcdir" destination="outdir/">
+                    <item>lib1.so</item>
+                    <item destination="docs/README.md">my_doc.md</item>
+                </group>
+            </copy>
+        </tasks>"#
+            .parse()
+            .unwrap();
+        let tasks = parse_input(&Runtime::default(), &root).unwrap();
+        let expected = [
+            "copy srcdir/lib1.so -> outdir/lib1.so",
+            "copy srcdir/my_doc.md -> outdir/docs/README.md",
+        ];
+        assert_eq!(expected.to_vec(), tasks[0].describe());
+    }
+
+    #[test]
+    fn schema_declares_tasks() {
+        let schema: Element = SCHEMA.parse().unwrap();
+        let names: Vec<&str> = schema
+            .children()
+            .filter_map(|child| child.attr("name"))
+            .collect();
+        for name in [
+            "tasks",
+            "copy_group",
+            "copy_item",
+            "strip_group",
+            "strip_item",
+        ] {
+            assert!(names.contains(&name), "{} missing from schema", name);
+        }
+    }
+
+    #[test]
+    fn schema_from_builtin_descriptors() {
+        let builtin = builtin_descriptors().unwrap();
+        let tasks: Vec<&TaskDescriptor> = builtin.iter().collect();
+        assert_eq!(SCHEMA, generate_schema(&tasks));
+    }
+
+    #[test]
+    fn schema_declares_loaded_tasks() {
+        let descriptor = TaskDescriptor::from_reader(
+            r#"
+name: lint
+command:
+  command_line:
+    default: lint $source
+element:
+  attributes:
+    - name: source
+      type: path
+      allow_group: prefix
+      role: source
+      required: true
+"#
+            .as_bytes(),
+        )
+        .unwrap();
+        let schema: Element = schema(&[Rc::new(descriptor)]).unwrap().parse().unwrap();
+        let names: Vec<&str> = schema
+            .children()
+            .filter_map(|child| child.attr("name"))
+            .collect();
+        for name in ["copy_group", "lint_group", "lint_item"] {
+            assert!(names.contains(&name), "{} missing from schema", name);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_task_links_source_relatively() {
+        let root = fixture("symlink_task", &[("build/lib/libfoo.so.1", "foo")]);
+        std::os::unix::fs::symlink("libfoo.so.1", root.join("build/lib/libfoo.so")).unwrap();
+        let manifest = format!(
+            r#"<tasks xmlns="https://github.com/glecaros/bf">
+                <symlink>
+                    <group source="{0}/build/lib" destination="{0}/out/">
+                        <item>libfoo.so</item>
+                        <item destination="{0}/out/libfoo.so.1">libfoo.so.1</item>
+                    </group>
+                    <item destination="{0}/out/current">{0}/build/lib</item>
+                </symlink>
+            </tasks>"#,
+            root.to_string_lossy()
+        );
+        let root_element: Element = manifest.parse().unwrap();
+        for _ in 0..2 {
+            for task in parse_input(&Runtime::default(), &root_element).unwrap() {
+                task.run().unwrap();
+            }
+        }
+        for name in ["libfoo.so", "libfoo.so.1"] {
+            let target = fs::read_link(root.join("out").join(name)).unwrap();
+            assert_eq!(Path::new("../build/lib").join(name), target);
+        }
+        let target = fs::read_link(root.join("out/current")).unwrap();
+        assert_eq!(Path::new("../build/lib"), target);
+        let moved = root.with_extension("moved");
+        let _ = fs::remove_dir_all(&moved);
+        fs::rename(&root, &moved).unwrap();
+        assert_eq!(
+            "foo",
+            fs::read_to_string(moved.join("out/libfoo.so")).unwrap()
+        );
+        fs::remove_dir_all(moved).unwrap();
+    }
 }
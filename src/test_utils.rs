@@ -0,0 +1,14 @@
+use std::{fs, path::PathBuf};
+
+/// Creates an empty directory for the test `name` under the system temporary
+/// directory and writes `files`, given as relative path and contents, into it.
+pub fn fixture(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("bf_{}_{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    for (file, contents) in files {
+        let path = root.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+    root
+}
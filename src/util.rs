@@ -1,13 +1,21 @@
 use std::{
     collections::HashMap,
-    env,
+    env, fs,
     path::{Path, PathBuf},
 };
 
-use log::debug;
+use bf_codegen::command::OCTAL_MODE_PATTERN;
+use log::{debug, warn};
 use minidom::Element;
+use regex::Regex;
 
-use crate::{error::Error, internal_error, interpolation::interpolate, runtime::Runtime};
+use crate::{
+    condition::{numeric_comparisons, referenced_variables, with_functions},
+    error::{Error, ErrorContext},
+    internal_error,
+    interpolation::interpolate,
+    runtime::Runtime,
+};
 
 pub struct WorkingDirGuard {
     original_dir: PathBuf,
@@ -37,6 +45,9 @@ impl Drop for WorkingDirGuard {
 }
 
 pub const ATTR_CONDITION: &str = "condition";
+pub const ATTR_REQUIRED: &str = "required";
+pub const ATTR_EXCLUDE: &str = "exclude";
+pub const ELEMENT_EXCLUDE: &str = "exclude";
 
 pub fn interpolate_attribute(
     name: &str,
@@ -47,23 +58,84 @@ pub fn interpolate_attribute(
         .attr(name)
         .map(|v| {
             let variables = runtime.variables.clone().into_iter().collect();
-            interpolate(v, &variables)
+            interpolate(v, &variables, runtime.undefined_env)
         })
         .transpose()
 }
 
+pub fn interpolate_text(element: &Element, runtime: &Runtime) -> Result<Option<String>, Error> {
+    let text = element.text();
+    let text = text.trim();
+    if text.is_empty() {
+        Ok(None)
+    } else {
+        let variables = runtime.variables.clone().into_iter().collect();
+        interpolate(text, &variables, runtime.undefined_env).map(Some)
+    }
+}
+
+/// Values of a list parameter, read from a `;` separated attribute followed by
+/// any child elements named after the parameter.
+pub fn interpolate_list(
+    name: &str,
+    element: &Element,
+    runtime: &Runtime,
+) -> Result<Option<Vec<String>>, Error> {
+    let mut values = Vec::new();
+    let attribute = interpolate_attribute(name, element, runtime)?;
+    if let Some(attribute) = &attribute {
+        values.extend(
+            attribute
+                .split(';')
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(String::from),
+        );
+    }
+    let children: Vec<&Element> = element.children().filter(|c| c.name() == name).collect();
+    for child in &children {
+        let value = interpolate_text(child, runtime)?
+            .ok_or_else(|| internal_error!("Empty <{}> element", name))?;
+        values.push(value);
+    }
+    if attribute.is_none() && children.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(values))
+    }
+}
+
+/// Values inherited from the enclosing group followed by the element's own.
+pub fn append_list<T: Clone>(parent: Option<&Vec<T>>, values: Option<Vec<T>>) -> Option<Vec<T>> {
+    match (parent, values) {
+        (Some(parent), Some(values)) => Some(parent.iter().cloned().chain(values).collect()),
+        (Some(parent), None) => Some(parent.clone()),
+        (None, values) => values,
+    }
+}
+
 pub fn evaluate_condition(condition: Option<&str>, runtime: &Runtime) -> Result<bool, Error> {
     use eval::Expr;
     let variables: HashMap<String, String> = runtime.variables.clone().into_iter().collect();
     debug!("variables: {:?}", variables);
     if let Some(condition) = condition {
         debug!("Evaluating condition {}", &condition);
-        let expr = &variables
-            .into_iter()
-            .fold(Expr::new(condition), |expr, (name, value)| {
-                expr.value(name, value)
-            });
-        let value = expr.exec().map_err(|e| e.to_string())?;
+        for name in referenced_variables(condition) {
+            if !variables.contains_key(&name) {
+                return Err(internal_error!(
+                    "Variable {} used in condition '{}' is not defined",
+                    name,
+                    condition
+                ));
+            }
+        }
+        let expr = &variables.into_iter().fold(
+            with_functions(Expr::new(numeric_comparisons(condition))),
+            |expr, (name, value)| expr.value(name, value),
+        );
+        let value = expr
+            .exec()
+            .map_err(|e| internal_error!("Invalid condition '{}': {}", condition, e))?;
         value.as_bool().ok_or(internal_error!(
             "Expression {} does not evaluate to bool",
             &condition
@@ -82,8 +154,59 @@ pub fn evaluate_condition_from_element(
     evaluate_condition(condition, runtime)
 }
 
+/// The `os`, `arch` and `condition` keys of a command line variant.
+pub type VariantKey<'a> = (Option<&'a str>, Option<&'a str>, Option<&'a str>);
+
+fn variable<'a>(runtime: &'a Runtime, name: &str) -> Option<&'a str> {
+    runtime
+        .variables
+        .iter()
+        .rev()
+        .find(|(variable, _)| variable == name)
+        .map(|(_, value)| value.as_str())
+}
+
+/// Index of the first variant matching the runtime, or `variants.len()` for
+/// the default command line when there is one.
+pub fn select_command_variant(
+    task: &str,
+    runtime: &Runtime,
+    variants: &[VariantKey],
+    has_default: bool,
+) -> Result<usize, Error> {
+    let os = variable(runtime, "os");
+    let arch = variable(runtime, "arch");
+    for (index, (variant_os, variant_arch, condition)) in variants.iter().enumerate() {
+        if variant_os.is_some() && *variant_os != os {
+            continue;
+        }
+        if variant_arch.is_some() && *variant_arch != arch {
+            continue;
+        }
+        if evaluate_condition(*condition, runtime)? {
+            debug!("Task {} uses command line variant {}", task, index);
+            return Ok(index);
+        }
+    }
+    if has_default {
+        Ok(variants.len())
+    } else {
+        Err(internal_error!(
+            "No command line of task '{}' matches os '{}' and arch '{}'",
+            task,
+            os.unwrap_or_default(),
+            arch.unwrap_or_default()
+        ))
+    }
+}
+
 pub trait ApplyPrefix {
+    /// Joins the value to `prefix`, or yields `prefix` alone when there is no
+    /// value, so it can be inherited.
     fn apply_prefix(&self, prefix: &Self) -> Self;
+
+    /// Joins the value to `prefix`, leaving a missing value missing.
+    fn apply_prefix_if_set(&self, prefix: &Self) -> Self;
 }
 
 impl ApplyPrefix for Option<PathBuf> {
@@ -103,11 +226,402 @@ impl ApplyPrefix for Option<PathBuf> {
             self.as_ref().map(PathBuf::from)
         }
     }
+
+    fn apply_prefix_if_set(&self, prefix: &Self) -> Self {
+        self.as_ref().and(self.apply_prefix(prefix))
+    }
+}
+
+pub fn parse_bool(name: &str, value: &str) -> Result<bool, Error> {
+    match value {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => Err(internal_error!(
+            "Invalid value for '{}': {} (expected 'true' or 'false')",
+            name,
+            value
+        )),
+    }
+}
+
+pub fn parse_int(name: &str, value: &str) -> Result<i64, Error> {
+    value.parse().map_err(|_| {
+        internal_error!(
+            "Invalid value for '{}': {} (expected an integer)",
+            name,
+            value
+        )
+    })
+}
+
+/// Unix permission bits, written in octal in manifests and descriptions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileMode(pub u32);
+
+impl std::fmt::Display for FileMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04o}", self.0)
+    }
+}
+
+/// Accepts the values matched by the `octal_mode` type of the schema.
+pub fn parse_octal_mode(name: &str, value: &str) -> Result<FileMode, Error> {
+    let pattern = Regex::new(&format!("^(?:{})$", OCTAL_MODE_PATTERN))?;
+    let digits = value.strip_prefix("0o").unwrap_or(value);
+    Some(digits)
+        .filter(|_| pattern.is_match(value))
+        .and_then(|digits| u32::from_str_radix(digits, 8).ok())
+        .map(FileMode)
+        .ok_or_else(|| {
+            internal_error!(
+                "Invalid value for '{}': {} (expected an octal mode such as 0755)",
+                name,
+                value
+            )
+        })
+}
+
+pub fn parse_enum(name: &str, value: String, allowed: &[&str]) -> Result<String, Error> {
+    if allowed.contains(&value.as_str()) {
+        Ok(value)
+    } else {
+        Err(internal_error!(
+            "Invalid value for '{}': {} (expected one of {})",
+            name,
+            value,
+            allowed.join(", ")
+        ))
+    }
+}
+
+pub fn glob_required(element: &Element) -> Result<bool, Error> {
+    let required = element
+        .attr(ATTR_REQUIRED)
+        .map(|value| parse_bool(ATTR_REQUIRED, value))
+        .transpose()?;
+    Ok(required.unwrap_or(false))
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Exclusions {
+    patterns: Vec<glob::Pattern>,
+}
+
+impl Exclusions {
+    pub fn create(
+        element: &Element,
+        parent: Option<&Exclusions>,
+        runtime: &Runtime,
+    ) -> Result<Exclusions, Error> {
+        let mut patterns = parent
+            .map(|parent| parent.patterns.clone())
+            .unwrap_or_default();
+        if let Some(value) = interpolate_attribute(ATTR_EXCLUDE, element, runtime)? {
+            for pattern in value.split(';').map(str::trim).filter(|p| !p.is_empty()) {
+                patterns.push(glob::Pattern::new(pattern)?);
+            }
+        }
+        for child in element.children().filter(|c| c.name() == ELEMENT_EXCLUDE) {
+            let pattern = interpolate_text(child, runtime)?
+                .ok_or_else(|| internal_error!("Empty <{}> element", ELEMENT_EXCLUDE))?;
+            patterns.push(glob::Pattern::new(&pattern)?);
+        }
+        Ok(Exclusions { patterns })
+    }
+
+    /// Patterns containing a path separator are matched against the whole
+    /// relative path, any other pattern against the file name alone.
+    pub fn is_excluded(&self, relative: &Path) -> bool {
+        let file_name = relative.file_name().map(Path::new).unwrap_or(relative);
+        self.patterns.iter().any(|pattern| {
+            if pattern.as_str().contains('/') || pattern.as_str().contains('\\') {
+                pattern.matches_path(relative)
+            } else {
+                pattern.matches_path(file_name)
+            }
+        })
+    }
+}
+
+pub fn expand_glob(
+    pattern: &Path,
+    root: &Path,
+    required: bool,
+    exclude: &Exclusions,
+) -> Result<Option<Vec<(PathBuf, PathBuf)>>, Error> {
+    if pattern.exists() {
+        return Ok(None);
+    }
+    let pattern = pattern.to_string_lossy();
+    if glob::Pattern::escape(&pattern) == pattern {
+        return Ok(None);
+    }
+    debug!("Expanding pattern {}", &pattern);
+    let mut matches = Vec::new();
+    for entry in glob::glob(&pattern)? {
+        let path = entry?;
+        if !path.is_file() {
+            continue;
+        }
+        let relative = match path.strip_prefix(root) {
+            Ok(relative) => relative.to_path_buf(),
+            Err(_) => path.file_name().map(PathBuf::from).unwrap_or_default(),
+        };
+        if exclude.is_excluded(&relative) {
+            debug!("  excluded {}", path.to_string_lossy());
+            continue;
+        }
+        debug!("  matched {}", path.to_string_lossy());
+        matches.push((path, relative));
+    }
+    if matches.is_empty() {
+        if required {
+            return Err(internal_error!(
+                "Required pattern {} did not match any file",
+                &pattern
+            ));
+        }
+        warn!("Pattern {} did not match any file", &pattern);
+    }
+    Ok(Some(matches))
+}
+
+/// A destination that ends with a path separator or that is an existing
+/// directory receives the source under its own name. A link to the source
+/// itself, such as one left by an earlier `symlink` task, is not a directory
+/// to put the source in.
+pub fn resolve_destination(source: &Path, destination: &Path) -> PathBuf {
+    let names_directory = destination
+        .to_string_lossy()
+        .ends_with(std::path::MAIN_SEPARATOR)
+        || (destination.is_dir() && !links_to(destination, source));
+    match source.file_name() {
+        Some(file_name) if names_directory => destination.join(file_name),
+        _ => destination.to_path_buf(),
+    }
+}
+
+fn links_to(link: &Path, path: &Path) -> bool {
+    let is_link =
+        matches!(fs::symlink_metadata(link), Ok(metadata) if metadata.file_type().is_symlink());
+    is_link
+        && matches!(
+            (fs::canonicalize(link), fs::canonicalize(path)),
+            (Ok(link), Ok(path)) if link == path
+        )
+}
+
+/// Used by `bf validate` to report an item whose input does not exist: its
+/// source or, for tasks without one, the path it changes. Paths written by
+/// earlier items, collected in `produced`, count as existing.
+pub fn check_item(context: ErrorContext, produced: &mut Vec<PathBuf>) -> Option<Error> {
+    let (input, output) = match &context.source {
+        Some(source) => (Some(source), context.destination.as_ref()),
+        None => (context.destination.as_ref(), None),
+    };
+    if let Some(output) = output {
+        produced.push(output.clone());
+    }
+    match input {
+        Some(input)
+            if input.symlink_metadata().is_err()
+                && !produced.iter().any(|path| input.starts_with(path)) =>
+        {
+            let what = if context.source.is_some() {
+                "Source"
+            } else {
+                "Path"
+            };
+            Some(
+                internal_error!("{} {} does not exist", what, input.to_string_lossy())
+                    .with_context(context),
+            )
+        }
+        _ => None,
+    }
+}
+
+pub trait Describe {
+    fn describe(&self) -> String;
+}
+
+impl Describe for PathBuf {
+    fn describe(&self) -> String {
+        self.to_string_lossy().to_string()
+    }
+}
+
+impl Describe for String {
+    fn describe(&self) -> String {
+        self.clone()
+    }
+}
+
+impl Describe for bool {
+    fn describe(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl Describe for i64 {
+    fn describe(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl Describe for FileMode {
+    fn describe(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl<T: Describe> Describe for Vec<T> {
+    fn describe(&self) -> String {
+        self.iter()
+            .map(Describe::describe)
+            .collect::<Vec<String>>()
+            .join(";")
+    }
+}
+
+impl<T: Describe> Describe for Option<T> {
+    fn describe(&self) -> String {
+        self.as_ref()
+            .map(Describe::describe)
+            .unwrap_or_else(|| String::from("<none>"))
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::{runtime::Runtime, util::evaluate_condition};
+    use std::path::{Path, PathBuf};
+
+    use minidom::Element;
+    use regex::Regex;
+
+    use crate::{
+        error::ErrorContext,
+        runtime::Runtime,
+        task::SCHEMA,
+        test_utils::fixture,
+        util::{
+            append_list, check_item, evaluate_condition, expand_glob, interpolate_list, parse_enum,
+            parse_int, parse_octal_mode, select_command_variant, Exclusions, FileMode,
+        },
+    };
+
+    fn glob_fixture(name: &str) -> PathBuf {
+        let files = ["lib/a.so", "lib/b.so", "lib/c.txt", "lib/nested/d.so"].map(|file| (file, ""));
+        fixture(&format!("glob_{}", name), &files)
+    }
+
+    #[test]
+    fn check_item_reports_missing() {
+        let root = glob_fixture("check");
+        let present = root.join("lib/a.so");
+        let missing = root.join("lib/missing.so");
+        let output = root.join("out/a.so");
+        let mut produced = Vec::new();
+        let context = |source, destination| ErrorContext::new("copy", 0, source, destination);
+        assert!(check_item(context(Some(&present), Some(&output)), &mut produced).is_none());
+        assert!(check_item(context(Some(&missing), None), &mut produced).is_some());
+        assert!(check_item(context(None, Some(&output)), &mut produced).is_none());
+        assert!(check_item(context(None, Some(&missing)), &mut produced).is_some());
+        assert!(check_item(context(None, None), &mut produced).is_none());
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn expand_glob_not_a_pattern() {
+        let exclude = Exclusions::default();
+        let result = expand_glob(Path::new("lib/a.so"), Path::new(""), true, &exclude);
+        assert!(matches!(result, Ok(None)));
+    }
+
+    #[test]
+    fn expand_glob_keeps_relative_path() {
+        let root = glob_fixture("relative");
+        let exclude = Exclusions::default();
+        let matches = expand_glob(&root.join("lib/*.so"), &root, false, &exclude);
+        let mut matches = matches.unwrap().unwrap();
+        matches.sort();
+        let relative: Vec<PathBuf> = matches.into_iter().map(|(_, relative)| relative).collect();
+        assert_eq!(
+            vec![PathBuf::from("lib/a.so"), PathBuf::from("lib/b.so")],
+            relative
+        );
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn expand_glob_recursive() {
+        let root = glob_fixture("recursive");
+        let exclude = Exclusions::default();
+        let matches = expand_glob(&root.join("**/*.so"), &root, false, &exclude);
+        assert_eq!(3, matches.unwrap().unwrap().len());
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn expand_glob_existing_literal_path() {
+        let root = glob_fixture("literal");
+        std::fs::write(root.join("lib/[a].so"), "").unwrap();
+        let exclude = Exclusions::default();
+        let result = expand_glob(&root.join("lib/[a].so"), &root, true, &exclude);
+        assert!(matches!(result, Ok(None)));
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn expand_glob_required_without_matches() {
+        let root = glob_fixture("required");
+        let exclude = Exclusions::default();
+        let result = expand_glob(&root.join("*.dll"), &root, true, &exclude);
+        assert!(matches!(result, Err(_)));
+        let result = expand_glob(&root.join("*.dll"), &root, false, &exclude);
+        assert!(matches!(result, Ok(Some(matches)) if matches.is_empty()));
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn exclusions_inherited_and_merged() {
+        let runtime = Runtime::default();
+        let outer: Element =
+            r#"<group xmlns="https://github.com/glecaros/bf" exclude="*.debug; test_*"/>"#
+                .parse()
+                .unwrap();
+        let inner: Element = r#"<group xmlns="https://github.com/glecaros/bf">
+            <exclude>nested/*.txt</exclude>
+        </group>"#
+            .parse()
+            .unwrap();
+        let outer = Exclusions::create(&outer, None, &runtime).unwrap();
+        let inner = Exclusions::create(&inner, Some(&outer), &runtime).unwrap();
+        assert!(inner.is_excluded(Path::new("lib/a.so.debug")));
+        assert!(inner.is_excluded(Path::new("lib/test_a.so")));
+        assert!(inner.is_excluded(Path::new("nested/notes.txt")));
+        assert!(!inner.is_excluded(Path::new("lib/notes.txt")));
+        assert!(!outer.is_excluded(Path::new("nested/notes.txt")));
+    }
+
+    #[test]
+    fn expand_glob_with_exclusions() {
+        let root = glob_fixture("exclusions");
+        let runtime = Runtime::default();
+        let element: Element = r#"<group xmlns="https://github.com/glecaros/bf" exclude="b.*"/>"#
+            .parse()
+            .unwrap();
+        let exclude = Exclusions::create(&element, None, &runtime).unwrap();
+        let matches = expand_glob(&root.join("lib/*"), &root, false, &exclude);
+        let mut matches = matches.unwrap().unwrap();
+        matches.sort();
+        let relative: Vec<PathBuf> = matches.into_iter().map(|(_, relative)| relative).collect();
+        assert_eq!(
+            vec![PathBuf::from("lib/a.so"), PathBuf::from("lib/c.txt")],
+            relative
+        );
+        std::fs::remove_dir_all(root).unwrap();
+    }
 
     #[test]
     fn evaluate_condition_no_condition() {
@@ -136,9 +650,69 @@ mod test {
         let runtime = Runtime::default();
         const CONDITION: &str = "var == 'value'";
         let result = evaluate_condition(Some(CONDITION), &runtime);
-        assert!(matches!(result, Ok(_)));
-        let result = result.unwrap();
-        assert!(!result);
+        assert!(matches!(result, Err(_)));
+    }
+
+    #[test]
+    fn evaluate_condition_test_functions() {
+        let mut runtime = Runtime::default();
+        for (name, value) in [("arch", "aarch64"), ("version", "1.10.2"), ("jobs", "12")] {
+            runtime
+                .variables
+                .push((String::from(name), String::from(value)));
+        }
+        let evaluate = |condition| evaluate_condition(Some(condition), &runtime).unwrap();
+        assert!(evaluate("defined(arch) && defined(config) == false"));
+        assert!(evaluate("in(arch, 'x86_64', 'aarch64')"));
+        assert!(evaluate(
+            "matches(arch, '^aarch') && matches(arch, '^x86') == false"
+        ));
+        assert!(evaluate("version_ge(version, '1.9')"));
+        assert!(evaluate("jobs > 9"));
+        assert!(evaluate(
+            "exists('Cargo.toml') && exists('missing.toml') == false"
+        ));
+    }
+
+    #[test]
+    fn evaluate_condition_test_number_like_strings() {
+        let mut runtime = Runtime::default();
+        for (name, value) in [("level", "2"), ("version", "1.10")] {
+            runtime
+                .variables
+                .push((String::from(name), String::from(value)));
+        }
+        let evaluate = |condition| evaluate_condition(Some(condition), &runtime).unwrap();
+        assert!(evaluate("level == '2'"));
+        assert!(evaluate("level == 2 && level < 10"));
+        assert!(evaluate("num(level) + 1 == 3"));
+        assert!(evaluate("version_ge(version, '1.9')"));
+        assert!(evaluate("version == '1.10'"));
+        assert!(evaluate("matches(version, '^1\\.10')"));
+    }
+
+    #[test]
+    fn select_command_variant_test() {
+        let mut runtime = Runtime::default();
+        for (name, value) in [("os", "linux"), ("arch", "x86_64"), ("arch", "aarch64")] {
+            runtime
+                .variables
+                .push((String::from(name), String::from(value)));
+        }
+        let variants = [
+            (Some("windows"), None, None),
+            (Some("linux"), Some("aarch64"), Some("defined(sysroot)")),
+            (Some("linux"), Some("aarch64"), None),
+        ];
+        let result = select_command_variant("strip", &runtime, &variants, true);
+        assert_eq!(result.unwrap(), 2);
+        let result = select_command_variant("strip", &runtime, &variants[..2], true);
+        assert_eq!(result.unwrap(), 2);
+        let result = select_command_variant("strip", &runtime, &variants[..2], false);
+        assert_eq!(
+            result.unwrap_err().message,
+            "No command line of task 'strip' matches os 'linux' and arch 'aarch64'"
+        );
     }
 
     #[test]
@@ -161,4 +735,62 @@ mod test {
         let result = evaluate_condition(Some(CONDITION), &runtime);
         assert!(matches!(result, Err(_)));
     }
+
+    #[test]
+    fn typed_parameters() {
+        assert_eq!(9, parse_int("level", "9").unwrap());
+        assert!(matches!(parse_int("level", "high"), Err(_)));
+        assert_eq!(FileMode(0o755), parse_octal_mode("mode", "0755").unwrap());
+        assert_eq!(FileMode(0o644), parse_octal_mode("mode", "0o644").unwrap());
+        assert_eq!("0600", parse_octal_mode("mode", "600").unwrap().to_string());
+        assert!(matches!(parse_octal_mode("mode", "0899"), Err(_)));
+        assert!(matches!(parse_octal_mode("mode", "17777"), Err(_)));
+        let schema: Element = SCHEMA.parse().unwrap();
+        let pattern = schema
+            .children()
+            .find(|child| child.attr("name") == Some("octal_mode"))
+            .and_then(|mode| mode.children().next())
+            .and_then(|restriction| restriction.children().next())
+            .and_then(|pattern| pattern.attr("value"))
+            .unwrap();
+        let schema = Regex::new(&format!("^(?:{})$", pattern)).unwrap();
+        for value in [
+            "7", "0755", "00755", "0o7777", "+755", "0o", "0o07777", "000755", "",
+        ] {
+            let parsed = parse_octal_mode("mode", value);
+            assert_eq!(schema.is_match(value), parsed.is_ok(), "{}", value);
+        }
+        assert!(matches!(parse_octal_mode("mode", "+755"), Err(_)));
+        let allowed = ["fast", "best"];
+        assert!(matches!(
+            parse_enum("speed", String::from("best"), &allowed),
+            Ok(_)
+        ));
+        assert!(matches!(
+            parse_enum("speed", String::from("slow"), &allowed),
+            Err(_)
+        ));
+    }
+
+    #[test]
+    fn list_parameters() {
+        let runtime = Runtime::default();
+        let element: Element = r#"<item xmlns="bf" defines="A; B;"><defines>C</defines></item>"#
+            .parse()
+            .unwrap();
+        let defines = interpolate_list("defines", &element, &runtime).unwrap();
+        let expected = ["A", "B", "C"].map(String::from).to_vec();
+        assert_eq!(Some(expected), defines);
+        assert_eq!(
+            None,
+            interpolate_list("missing", &element, &runtime).unwrap()
+        );
+        let parent = vec![1, 2];
+        assert_eq!(
+            Some(vec![1, 2, 3]),
+            append_list(Some(&parent), Some(vec![3]))
+        );
+        assert_eq!(Some(vec![1, 2]), append_list(Some(&parent), None));
+        assert_eq!(None, append_list::<i64>(None, None));
+    }
 }
@@ -0,0 +1,316 @@
+use std::{
+    collections::HashMap,
+    env::consts,
+    path::{self, Path},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use log::debug;
+use minidom::Element;
+
+use crate::{
+    error::{Error, Location},
+    internal_error,
+    position::Positions,
+    runtime::{Runtime, VERSION},
+    util::parse_bool,
+};
+
+pub const ELEMENT_VARIABLES: &str = "variables";
+const ELEMENT_VARIABLE: &str = "variable";
+const ATTR_STRICT: &str = "strict";
+const ATTR_NAME: &str = "name";
+const ATTR_DEFAULT: &str = "default";
+const ATTR_VALUES: &str = "values";
+const ATTR_DESCRIPTION: &str = "description";
+/// Names of the variables returned by `builtin_variables`.
+const BUILTIN_VARIABLES: [&str; 7] = [
+    "os",
+    "arch",
+    "family",
+    "manifest_dir",
+    "working_dir",
+    "bf_version",
+    "timestamp",
+];
+
+#[derive(Debug)]
+pub struct VariableDeclaration {
+    pub name: String,
+    pub default: Option<String>,
+    pub values: Option<Vec<String>>,
+    pub description: Option<String>,
+    pub location: Option<Location>,
+}
+
+impl VariableDeclaration {
+    fn parse(element: &Element, location: Option<Location>) -> Result<VariableDeclaration, Error> {
+        let name = element
+            .attr(ATTR_NAME)
+            .ok_or_else(|| internal_error!("Variable declaration without a name"))?;
+        let values = element.attr(ATTR_VALUES).map(|values| {
+            values
+                .split(';')
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(String::from)
+                .collect()
+        });
+        Ok(VariableDeclaration {
+            name: String::from(name),
+            default: element.attr(ATTR_DEFAULT).map(String::from),
+            values,
+            description: element.attr(ATTR_DESCRIPTION).map(String::from),
+            location,
+        })
+    }
+
+    fn validate(&self, value: &str) -> Result<(), Error> {
+        match &self.values {
+            Some(values) if !values.iter().any(|allowed| allowed == value) => {
+                let description = self
+                    .description
+                    .as_ref()
+                    .map(|description| format!(" ({})", description))
+                    .unwrap_or_default();
+                let error = internal_error!(
+                    "Invalid value '{}' for variable {}{}, expected one of: {}",
+                    value,
+                    self.name,
+                    description,
+                    values.join(", ")
+                );
+                match &self.location {
+                    Some(location) => Err(error.with_location(location.clone())),
+                    None => Err(error),
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Declarations {
+    pub strict: bool,
+    pub variables: Vec<VariableDeclaration>,
+}
+
+impl Declarations {
+    pub fn parse(element: &Element, positions: &Positions) -> Result<Declarations, Error> {
+        let strict = element
+            .attr(ATTR_STRICT)
+            .map(|value| parse_bool(ATTR_STRICT, value))
+            .transpose()?
+            .unwrap_or(false);
+        let variables = element
+            .children()
+            .enumerate()
+            .map(|(index, child)| {
+                let location = positions
+                    .children
+                    .get(index)
+                    .and_then(|positions| positions.location.clone());
+                match child.name() {
+                    ELEMENT_VARIABLE => VariableDeclaration::parse(child, location.clone()),
+                    _ => Err(internal_error!("Invalid element: {}", child.name())),
+                }
+                .map_err(|e| e.at(location))
+            })
+            .collect::<Result<Vec<VariableDeclaration>, Error>>()?;
+        Ok(Declarations { strict, variables })
+    }
+
+    /// Fills in declared defaults for the variables not given on the command
+    /// line, then checks the resulting values against the declarations.
+    /// Built-in variables need no declaration in strict mode, and keep their
+    /// value over a declared default, which only documents them.
+    pub fn apply(&self, runtime: &mut Runtime, builtins: &[(String, String)]) -> Result<(), Error> {
+        let provided: HashMap<String, String> = runtime.variables.clone().into_iter().collect();
+        if self.strict {
+            for name in provided.keys() {
+                if !BUILTIN_VARIABLES.contains(&name.as_str())
+                    && !self.variables.iter().any(|variable| &variable.name == name)
+                {
+                    return Err(internal_error!(
+                        "Variable {} is not declared by the manifest",
+                        name
+                    ));
+                }
+            }
+        }
+        let builtins: HashMap<&str, &str> = builtins
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect();
+        let mut variables = Vec::new();
+        for variable in &self.variables {
+            let builtin = builtins.get(variable.name.as_str());
+            match (provided.get(&variable.name), builtin, &variable.default) {
+                (Some(value), _, _) => variable.validate(value)?,
+                (None, Some(value), _) => variable.validate(value)?,
+                (None, None, Some(default)) => {
+                    debug!("Using default value for {}: {}", variable.name, default);
+                    variable.validate(default)?;
+                    variables.push((variable.name.clone(), default.clone()));
+                }
+                (None, None, None) => (),
+            }
+        }
+        variables.append(&mut runtime.variables);
+        runtime.variables = variables;
+        Ok(())
+    }
+}
+
+/// Variables describing the host and the invocation, available to every
+/// manifest unless they are overridden.
+pub fn builtin_variables(runtime: &Runtime) -> Result<Vec<(String, String)>, Error> {
+    let path_variable = |path: &Path| -> Result<String, Error> {
+        Ok(path::absolute(path)?.to_string_lossy().to_string())
+    };
+    let manifest = path::absolute(&runtime.input)?;
+    let manifest_dir = manifest.parent().unwrap_or(Path::new(""));
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let values = [
+        String::from(consts::OS),
+        String::from(consts::ARCH),
+        String::from(consts::FAMILY),
+        path_variable(manifest_dir)?,
+        path_variable(&runtime.working_directory)?,
+        String::from(VERSION),
+        timestamp.to_string(),
+    ];
+    Ok(BUILTIN_VARIABLES
+        .into_iter()
+        .map(String::from)
+        .zip(values)
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use minidom::Element;
+
+    use crate::{position::Positions, runtime::Runtime};
+
+    use super::{builtin_variables, Declarations};
+
+    fn declarations(strict: bool) -> Declarations {
+        let element: Element = format!(
+            r#"<variables xmlns="https://github.com/glecaros/bf" strict="{}">
+                <variable name="config" default="Release" values="Debug;Release" description="Build configuration"/>
+                <variable name="arch"/>
+            </variables>"#,
+            strict
+        )
+        .parse()
+        .unwrap();
+        Declarations::parse(&element, &Positions::default()).unwrap()
+    }
+
+    fn variable<'a>(runtime: &'a Runtime, name: &str) -> Option<&'a str> {
+        runtime
+            .variables
+            .iter()
+            .rev()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    #[test]
+    fn defaults_are_applied() {
+        let mut runtime = Runtime::default();
+        declarations(false).apply(&mut runtime, &[]).unwrap();
+        assert_eq!(Some("Release"), variable(&runtime, "config"));
+        assert_eq!(None, variable(&runtime, "arch"));
+    }
+
+    #[test]
+    fn command_line_overrides_default() {
+        let mut runtime = Runtime::default();
+        runtime
+            .variables
+            .push((String::from("config"), String::from("Debug")));
+        declarations(false).apply(&mut runtime, &[]).unwrap();
+        assert_eq!(Some("Debug"), variable(&runtime, "config"));
+    }
+
+    #[test]
+    fn value_not_allowed() {
+        let mut runtime = Runtime::default();
+        runtime
+            .variables
+            .push((String::from("config"), String::from("Profile")));
+        let result = declarations(false).apply(&mut runtime, &[]);
+        assert!(matches!(result, Err(_)));
+    }
+
+    #[test]
+    fn unknown_variable() {
+        let mut runtime = Runtime::default();
+        runtime
+            .variables
+            .push((String::from("other"), String::from("value")));
+        assert!(matches!(
+            declarations(false).apply(&mut runtime, &[]),
+            Ok(_)
+        ));
+        let result = declarations(true).apply(&mut runtime, &[]);
+        assert!(matches!(result, Err(_)));
+    }
+
+    #[test]
+    fn builtin_variables_in_strict_mode() {
+        let mut runtime = Runtime {
+            input: std::env::temp_dir().join("manifest.xml"),
+            ..Runtime::default()
+        };
+        let builtins = builtin_variables(&runtime).unwrap();
+        runtime
+            .variables
+            .push((String::from("os"), String::from("windows")));
+        assert!(declarations(true).apply(&mut runtime, &builtins).is_ok());
+        let element: Element = r#"<variables xmlns="https://github.com/glecaros/bf">
+                <variable name="arch" values="sparc;mips"/>
+            </variables>"#
+            .parse()
+            .unwrap();
+        let declarations = Declarations::parse(&element, &Positions::default()).unwrap();
+        assert!(declarations.apply(&mut runtime, &builtins).is_err());
+    }
+
+    #[test]
+    fn builtin_variables_ignore_declared_default() {
+        let mut runtime = Runtime {
+            input: std::env::temp_dir().join("manifest.xml"),
+            ..Runtime::default()
+        };
+        let builtins = builtin_variables(&runtime).unwrap();
+        let element: Element = r#"<variables xmlns="https://github.com/glecaros/bf">
+                <variable name="os" default="plan9" description="Host operating system"/>
+            </variables>"#
+            .parse()
+            .unwrap();
+        let declarations = Declarations::parse(&element, &Positions::default()).unwrap();
+        assert!(matches!(
+            declarations.apply(&mut runtime, &builtins),
+            Ok(())
+        ));
+        assert!(runtime.variables.iter().all(|(name, _)| name != "os"));
+    }
+
+    #[test]
+    fn builtin_variables_describe_host() {
+        let mut runtime = Runtime::default();
+        runtime.input = std::env::temp_dir().join("manifest.xml");
+        runtime.variables = builtin_variables(&runtime).unwrap();
+        assert_eq!(Some(std::env::consts::OS), variable(&runtime, "os"));
+        assert_eq!(Some(std::env::consts::ARCH), variable(&runtime, "arch"));
+        assert_eq!(
+            Some(std::env::temp_dir().to_string_lossy().as_ref()),
+            variable(&runtime, "manifest_dir")
+        );
+        assert!(variable(&runtime, "timestamp").is_some());
+    }
+}